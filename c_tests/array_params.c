@@ -0,0 +1,38 @@
+// Arrays passed to functions decay to pointers to their first element
+
+int g[4];
+
+int sum(int a[], int n) {
+  int s = 0;
+  for (int i = 0; i < n; i++) s += a[i];
+  return s;
+}
+
+int first(int a[3]) { return a[0] + a[2]; }
+
+int row(int *r) { return r[1]; }
+
+// Function parameters decay too
+int apply(int f(int *), int *r) { return f(r); }
+
+int total(int m[][3], int rows) {
+  int s = 0;
+  for (int i = 0; i < rows; i++) s += m[i][2];
+  return s;
+}
+
+int main() {
+  int l[3] = {1, 2, 3};
+  g[0] = 4; g[1] = 5; g[2] = 6; g[3] = 7;
+  int m[2][3] = {{1, 2, 3}, {4, 5, 6}};
+  putchar('0' + sum(l, 3));
+  putchar('0' + sum(g, 3));
+  putchar('0' + first(l));
+  putchar('0' + row(&m[1][0]));
+  putchar('0' + apply(row, m[0]));
+  putchar('0' + total(m, 2));
+  putchar('0' + sizeof(int[3]));
+  int *p = g;
+  putchar('0' + p[3]);
+  putchar('\n');
+}
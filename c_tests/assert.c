@@ -0,0 +1,22 @@
+#include <assert.h>
+
+// Assertions which hold, so the program runs to the end.
+
+int fib(int n) {
+  assert(n >= 0);
+  if (n < 2) {
+    return n;
+  }
+  return fib(n - 1) + fib(n - 2);
+}
+
+int main() {
+  assert(fib(7) == 13);
+  assert(1);
+
+  for (int i = 0; i < 5; i++) {
+    assert(fib(i) <= i);
+    putchar('0' + fib(i));
+  }
+  putchar('\n');
+}
@@ -0,0 +1,7 @@
+// Inline BF that doesn't push the word it says it does stops the program
+int main() {
+  putchar('a');
+  int x = __bf("<", 0, 1);
+  putchar('b');
+  return x;
+}
@@ -0,0 +1,44 @@
+// Packed flag fields, the way a protocol header might be decoded.
+
+struct header {
+  unsigned version : 4;
+  unsigned flags : 3;
+  unsigned urgent : 1;
+  int delta : 4;
+  unsigned : 0;
+  unsigned length : 12;
+  int skew : 6; // Doesn't fit beside `length`, so it starts the next cell
+  int checksum;
+};
+
+void bump(struct header *h) {
+  h->version += 1;
+  h->delta -= 3;
+}
+
+int main() {
+  struct header h = {2, 5, 1, -2, 100, -9, 7};
+  putchar('0' + sizeof(struct header));
+
+  putchar('0' + h.version);
+  putchar('0' + h.flags);
+  putchar('0' + h.urgent);
+  putchar(h.delta == -2 ? '-' : '+');
+  putchar('0' + h.length / 10 % 10);
+  putchar(h.skew == -9 ? '-' : '+');
+
+  // Writes only touch their own bits
+  h.flags = 9;
+  putchar('0' + h.flags);
+  putchar('0' + h.version);
+  h.skew = 33; // Wraps around to -31
+  putchar(h.skew == -31 ? '-' : '+');
+  putchar('0' + h.length / 100);
+
+  bump(&h);
+  putchar('0' + h.version);
+  putchar('0' - h.delta);
+  putchar('0' + h.urgent);
+  putchar('0' + h.checksum);
+  putchar('\n');
+}
@@ -0,0 +1,28 @@
+// Block-scope `static` objects keep their value between calls, & block-scope
+// `extern` ones are the file-scope object
+static int calls = 10;
+
+int next() {
+  static int calls = 0;
+  calls++;
+  {
+    static int calls = 100;
+    calls--;
+  }
+  return calls;
+}
+
+int total() {
+  extern int g;
+  return g + calls;
+}
+
+int g = 20;
+
+int main() {
+  next();
+  next();
+  putchar('0' + next());
+  putchar('\n');
+  return total();
+}
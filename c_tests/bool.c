@@ -0,0 +1,29 @@
+#include <stdbool.h>
+
+// `_Bool` only ever holds 0 or 1.
+
+bool odd(int n) {
+  return n & 1;
+}
+
+int main() {
+  _Bool flags[3] = {5, 0, -1};
+  bool b = 42;
+  putchar('0' + b);
+
+  b = 0;
+  putchar('0' + b);
+
+  b += 7;
+  putchar('0' + b);
+
+  b++;
+  putchar('0' + b);
+
+  putchar('0' + (_Bool)256);
+  putchar('0' + odd(3) + odd(4));
+  putchar('0' + flags[0] + flags[1] + flags[2]);
+  putchar(true == 1 && !false ? 'Y' : 'N');
+  putchar('0' + sizeof(bool));
+  putchar('\n');
+}
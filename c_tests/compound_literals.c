@@ -0,0 +1,38 @@
+// Compound literals, as unnamed locals & globals
+
+struct pt { int x; int y; };
+
+int sum(int *a, int n) {
+  int s = 0;
+  for (int i = 0; i < n; i++) s += a[i];
+  return s;
+}
+
+int dist(struct pt p) { return p.x + p.y; }
+
+int *table = (int[]){3, 1, 4};
+struct pt *origin = &(struct pt){0, 0};
+
+int main() {
+  putchar('0' + sum((int[]){1, 2, 3}, 3));
+  putchar('0' + dist((struct pt){2, 5}));
+  putchar('0' + table[2]);
+  putchar('0' + origin->y);
+
+  int *p = (int[4]){9};
+  putchar('0' + p[0] + p[3]);
+
+  struct pt *q = &(struct pt){1, 2};
+  q->x = 4;
+  putchar('0' + q->x + q->y);
+
+  int n = (int){7};
+  putchar('0' + n);
+
+  for (int i = 0; i < 3; i++) {
+    int *c = (int[]){i, i};
+    c[1] += 1;
+    putchar('0' + c[0] + c[1]);
+  }
+  putchar('\n');
+}
@@ -0,0 +1,23 @@
+// `const` globals are folded into their reads, & keep a cell only if their
+// address is taken
+
+const int W = 80;
+const int H = W / 20;
+int const N = -3;
+const int A = 5; // Its address is taken, so it keeps a cell
+const int *pa = &A;
+const int table[3] = {1, 2, 3};
+struct pt { int x; int y; };
+const struct pt origin = {4, 6};
+
+int area(const int *p) { return *p * H; }
+
+int main() {
+  putchar('0' + W / 10);
+  putchar('0' + H);
+  putchar('0' - N);
+  putchar('0' + *pa + area(&A) / 10);
+  putchar('0' + table[2]);
+  putchar('0' + origin.y);
+  putchar('\n');
+}
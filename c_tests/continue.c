@@ -0,0 +1,53 @@
+// `continue` & `break` in every kind of loop, & in switches nested in loops
+
+int main() {
+  // Skips odd numbers; the step still runs after `continue`
+  for (int i = 0; i < 10; i++) {
+    if (i % 2) continue;
+    if (i == 8) break;
+    putchar('0' + i);
+  }
+  putchar(' ');
+
+  int i = 0;
+  while (i < 10) {
+    i++;
+    if (i % 3) continue;
+    if (i == 9) break;
+    putchar('0' + i);
+  }
+  putchar(' ');
+
+  i = 0;
+  do {
+    i++;
+    if (i == 2) continue;
+    putchar('0' + i);
+  } while (i < 4);
+  putchar(' ');
+
+  // `continue` & `break` only leave the innermost loop
+  for (int a = 1; a <= 3; a++) {
+    for (int b = 1; b <= 3; b++) {
+      if (b == a) continue;
+      if (b > a) break;
+      putchar('0' + a * b);
+    }
+  }
+  putchar(' ');
+
+  // `break` leaves the switch, but `continue` goes to the next iteration
+  for (int n = 0; n < 5; n++) {
+    switch (n) {
+    case 1:
+      continue;
+    case 3:
+      putchar('!');
+      break;
+    default:
+      putchar('0' + n);
+    }
+    putchar('.');
+  }
+  putchar('\n');
+}
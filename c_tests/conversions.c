@@ -0,0 +1,40 @@
+// Signed & unsigned arithmetic, comparisons & conversions between widths
+
+int main() {
+  int a = -7;
+  int b = 2;
+  unsigned u = 2;
+  char c = 200;
+  unsigned char uc = -1;
+
+  putchar('0' + (a < 0));
+  putchar('0' + (-2 < 0));
+  putchar('0' + (a < b));
+  putchar('0' + (a > b));
+  putchar('0' + (a <= -7));
+  putchar('0' + (a >= -6));
+  putchar('0' + (a < u)); // -7 becomes unsigned
+  putchar('0' - a / b);   // 3
+  putchar('0' - a % b);   // 1
+  putchar('0' + 7 / -2 + 6); // 3
+  putchar('0' - (a >> 1)); // 4
+  putchar('0' + (c == -56));
+  putchar('0' + (uc == 255));
+  putchar('0' + (c < 0));
+  c += 100;
+  putchar('0' + (c == 44));
+  uc++;
+  putchar('0' + uc);
+  int d = (char)300;
+  putchar('0' + (d == 44));
+  putchar('0' + ((unsigned char)-2 == 254));
+  int *p = &a;
+  int *q = &b;
+  putchar('0' + ((p - q) + (q - p) == 0));
+  a /= -7;
+  putchar('0' + a);
+  a = -9;
+  a %= 4;
+  putchar('0' - a);
+  putchar('\n');
+}
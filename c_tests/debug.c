@@ -0,0 +1,18 @@
+// Instrumentation, which the debug interpreter shows & release BF drops.
+
+int fact(int n) {
+  int acc = 1;
+  for (int i = 2; i <= n; i++) {
+    acc *= i;
+  }
+  __dump_locals();
+  return acc;
+}
+
+int main() {
+  __debug("start");
+  int f = fact(5);
+  __debug("after fact");
+  putchar(f);
+  putchar('\n');
+}
@@ -0,0 +1,21 @@
+// Enumeration constants are scoped like variables, which may hide them
+#include <stdbool.h>
+
+enum { RED, GREEN };
+
+int f() {
+  enum { K = 8 };
+  return K;
+}
+
+int K = 5;
+
+int main() {
+  int GREEN = 7;
+  int total = GREEN + f() - K;
+  {
+    enum { GREEN = 2 };
+    total = total * GREEN;
+  }
+  return total + GREEN + true;
+}
@@ -0,0 +1,6 @@
+// error: array is not assignable
+int main() {
+  int a[2];
+  a = 3;
+  return 0;
+}
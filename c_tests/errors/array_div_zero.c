@@ -0,0 +1,6 @@
+// error: division by zero in array size
+int a[4 / (2 - 2)];
+
+int main() {
+  return 0;
+}
@@ -0,0 +1,6 @@
+// error: unmatched `[` in `__bf` code
+
+int main() {
+  int x = 1;
+  return __bf("[", 1, 1, x);
+}
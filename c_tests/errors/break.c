@@ -0,0 +1,5 @@
+// error: break statement not within loop
+int main() {
+  break;
+  return 0;
+}
@@ -0,0 +1,8 @@
+// error: conflicting types for `x`
+
+extern long x;
+int x;
+
+int main() {
+  return x;
+}
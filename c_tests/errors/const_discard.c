@@ -0,0 +1,9 @@
+// error: discards `const`
+
+const int W = 3;
+
+int main() {
+  int *q = &W;
+  *q = 4;
+  return W;
+}
@@ -0,0 +1,8 @@
+// error: continue statement not within loop
+int main() {
+  switch (1) {
+  default:
+    continue;
+  }
+  return 0;
+}
@@ -0,0 +1,5 @@
+// error: unsupported type `float`
+int main() {
+  float f = 1;
+  return 0;
+}
@@ -0,0 +1,6 @@
+// error: unsupported operation `*` on `long`
+
+int main() {
+  long a = 70000;
+  return a * 2;
+}
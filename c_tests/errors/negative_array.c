@@ -0,0 +1,6 @@
+// error: array size is negative
+int a[1 - 2];
+
+int main() {
+  return 0;
+}
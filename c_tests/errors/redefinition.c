@@ -0,0 +1,12 @@
+// error: redefinition of `f`
+int f() {
+  return 1;
+}
+
+int f() {
+  return 2;
+}
+
+int main() {
+  return f();
+}
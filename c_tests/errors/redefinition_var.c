@@ -0,0 +1,7 @@
+// error: redefinition of `x`
+int x = 1;
+int x = 2;
+
+int main() {
+  return x;
+}
@@ -0,0 +1,6 @@
+// error: array size is not an integer constant expression
+int main() {
+  int n = 3;
+  int a[n];
+  return 0;
+}
@@ -0,0 +1,11 @@
+// Hex, octal & `\u` escapes are read whole, in strings & characters alike
+int main() {
+  int x = 0;
+  x = __bf("\x2b\53\u002B+", 1, 1, x);
+  putchar('A');
+  putchar('\x42');
+  putchar('\103');
+  putchar('\u0044');
+  putchar('\n');
+  return x;
+}
@@ -0,0 +1,17 @@
+// Exit codes: `main`'s return value, `exit()` & `abort()`.
+
+int check(int x) {
+  if (x > 3) {
+    putchar('!');
+    exit(x);
+  }
+  return x;
+}
+
+int main() {
+  for (int i = 0; i < 10; i++) {
+    putchar('0' + check(i));
+  }
+  putchar('?');
+  return 1;
+}
@@ -0,0 +1,25 @@
+// Functions reached only through pointers must survive -O1/-O2
+int add(int a, int b) {
+    return a + b;
+}
+
+int sub(int a, int b) {
+    return a - b;
+}
+
+int apply(int (*op)(int, int), int a, int b) {
+    return op(a, b);
+}
+
+int unused() {
+    return sub(1, 2);
+}
+
+int main() {
+    int (*op)(int, int) = sub;
+    int x = apply(add, 40, 2);
+    putchar('0' + x / 10);
+    putchar('0' + x % 10);
+    putchar('\n');
+    return op(x, 2);
+}
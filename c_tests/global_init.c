@@ -0,0 +1,20 @@
+// Globals initialized from constants, other globals, addresses & braced lists
+
+int u1;
+int a = 3;
+int u2[2];
+int b = a * 2;
+int arr[5] = {1, 2, 3, 4, 5};
+int *p = &a;
+int *q = &arr[3];
+int *r = arr + 1;
+struct pt { int x; int y; } s = {7, 8};
+int m[2][2] = {{1, 2}, {3, 4}};
+int late = 9;
+int main() {
+  putchar('0' + a); putchar('0' + b); putchar('0' + arr[4]);
+  putchar('0' + *p); putchar('0' + *q); putchar('0' + *r);
+  putchar('0' + s.y); putchar('0' + m[1][0]);
+  putchar('0' + late); putchar('0' + u1 + u2[1]);
+  putchar('\n');
+}
@@ -0,0 +1,22 @@
+// Hand-written BF & stack IR, spliced into C.
+
+int twice(int x) {
+  // Doubles the top of the stack, using the cell above it as scratch
+  return __bf("[->++<]>[-<+>]<", 1, 1, x);
+}
+
+int main() {
+  int a = '0';
+
+  // Adds 3 to its operand. Escapes work as in any string.
+  putchar(__stackir("Push(3)\nAdd", a));
+
+  // Clears a cell above the stack, then leaves nothing behind
+  __bf(">[-]<");
+
+  putchar(twice(33));
+
+  int b = __stackir("Push(64) Push(1) Add");
+  putchar(b);
+  putchar('\n');
+}
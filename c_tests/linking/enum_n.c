@@ -0,0 +1,4 @@
+// `N` here is an enumeration constant, which the other unit can't see
+enum { N = 3 };
+
+int three() { return N; }
@@ -0,0 +1,7 @@
+// Run with c_tests/linking/int_x.c, which disagrees about the type of `x`
+
+extern long x;
+
+int main() {
+  return x;
+}
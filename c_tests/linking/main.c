@@ -0,0 +1,26 @@
+// Run with c_tests/linking/util.c
+
+extern int total;
+int add(int n);
+int scale(); // Takes whatever util.c says it does
+
+// Declared again in util.c, as each unit has its own tags
+struct pair { int a; int b; };
+int sum(struct pair *p);
+
+static int calls = 0;
+
+static int twice(int n) {
+  calls++;
+  return add(n) + add(n);
+}
+
+int main() {
+  twice(2);
+  putchar('0' + total);
+  putchar('0' + calls);
+
+  struct pair p = { 3, 4 };
+  putchar('0' + sum(&p));
+  putchar('0' + scale(2, 4));
+}
@@ -0,0 +1,23 @@
+int total = 0;
+
+static int calls = 0;
+
+static int twice(int n) {
+  return n * 2;
+}
+
+int add(int n) {
+  calls++;
+  total = total + twice(n) / 2;
+  return total;
+}
+
+struct pair { int a; int b; };
+
+int sum(struct pair *p) {
+  return p->a + p->b;
+}
+
+int scale(int n, int k) {
+  return n * k;
+}
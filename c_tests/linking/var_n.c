@@ -0,0 +1,4 @@
+int N = 4;
+int three();
+
+int main() { return three() + N; }
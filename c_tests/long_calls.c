@@ -0,0 +1,18 @@
+// `long`s passed to & returned from functions, which take several words each
+
+long twice(long x) {
+  return x + x;
+}
+
+unsigned long long less(unsigned long long x, int y) {
+  return x - y;
+}
+
+int main() {
+  long a = 40000;
+  long b = twice(a);
+  putchar('0' + (b == 80000));
+  putchar('0' + (twice(-3) == -6));
+  putchar('0' + (less(0, 1) == 18446744073709551615));
+  return b - 79990;
+}
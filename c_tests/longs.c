@@ -0,0 +1,50 @@
+// Arithmetic on `long`s, which take two words, & `long long`s, which take four
+
+int main() {
+  long a = 70000;
+  long b = -5;
+  long c = a + b;          // 69995
+  putchar('0' + (c == 69995));
+  long d = b - a;          // -70005
+  putchar('0' + (d == -70005));
+  putchar('0' + (d < b));
+  putchar('0' + (a > b));
+  putchar('0' + (b >= -5));
+  putchar('0' + (b <= -6));
+  unsigned long u = 65535;
+  u = u + 1;
+  putchar('0' + (u == 65536));
+  u = 0;
+  u = u - 1;
+  putchar('0' + (u == 4294967295));
+  putchar('0' + (u > 1));
+  int i = -2;
+  long w = i;
+  putchar('0' + (w == -2));
+  int back = (int)(a + 1);
+  putchar('0' + (back == 4465));
+  long e = -a;
+  putchar('0' + (e == -70000));
+  putchar('0' + ((a & 65536) != 0));
+  putchar('0' + ((a | 1) == 70001));
+  putchar('0' + ((~a) == -70001));
+  a++;
+  ++a;
+  a += 65536;
+  a -= 1;
+  putchar('0' + (a == 135537));
+  if (a) putchar('1');
+  putchar('0' + !a);
+  long long big = 1;
+  big = big - 2;
+  putchar('0' + (big == -1));
+  unsigned long long all = big;
+  putchar('0' + (all == 18446744073709551615));
+  putchar('0' + (sizeof all == 4));
+  switch (a) {
+    case 135537: putchar('1'); break;
+    default: putchar('0');
+  }
+  putchar('\n');
+  return 0;
+}
@@ -0,0 +1,43 @@
+#include <stdlib.h>
+
+// Builds a linked list on the heap, then prints & frees it.
+
+int *push(int *list, int v) {
+  int *node = malloc(2);
+  node[0] = v;
+  node[1] = list;
+  return node;
+}
+
+int main() {
+  int *list = 0;
+
+  for (int i = 0; i < 10; i++) {
+    list = push(list, '0' + i);
+  }
+
+  while (list) {
+    int *next = list[1];
+    putchar(list[0]);
+    free(list);
+    list = next;
+  }
+
+  int *zeros = calloc(4, 1);
+  putchar('0' + zeros[0] + zeros[3]);
+
+  zeros = realloc(zeros, 8);
+  putchar('0' + zeros[0]);
+  free(zeros);
+
+  // Out of memory
+  int *huge = malloc(2000);
+  putchar(huge ? 'F' : 'N');
+
+  // Sizes which would wrap around
+  putchar(malloc(65535) ? 'F' : 'N');
+  putchar(calloc(256, 257) ? 'F' : 'N');
+  putchar(calloc(65535, 2) ? 'F' : 'N');
+  putchar(calloc(256, 256) ? 'F' : 'N'); // Wraps around to exactly 0
+  putchar('\n');
+}
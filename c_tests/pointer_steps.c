@@ -0,0 +1,31 @@
+// `++`, `--`, `+=` & `-=` move pointers by whole elements
+struct pt { int x; int y; };
+
+int main() {
+  long v[3];
+  long *p = v;
+  v[0] = 3;
+  v[1] = 7;
+  v[2] = 9;
+  p++;
+  putchar('0' + *p);
+  ++p;
+  putchar('0' + *p);
+  p -= 2;
+  p += 1;
+  putchar('0' + *p--);
+  putchar('0' + (p == v));
+
+  struct pt pts[3];
+  struct pt *q = pts;
+  q++;
+  q->y = 6;
+  ++q;
+  q->y = 8;
+  --q;
+  putchar('0' + pts[1].y);
+  q += 1;
+  putchar('0' + q->y);
+  putchar('\n');
+  return *p;
+}
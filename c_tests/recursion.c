@@ -0,0 +1,12 @@
+// Recurses 300 calls deep. With `-fstack-limit=1000`, the guard in each
+// function's prologue stops it with a stack overflow instead.
+
+int depth(int n) {
+  if (n == 0) return 0;
+  return depth(n - 1) + 1;
+}
+
+int main() {
+  putchar('0' + depth(300) / 100);
+  putchar('\n');
+}
@@ -0,0 +1,14 @@
+// Locals hide globals of the same name, even through their address
+
+int x = 1;
+
+int get() {
+  return x;
+}
+
+int main() {
+  int x = 5;
+  int *p = &x;
+  *p = *p + 1;
+  return x * 10 + get();
+}
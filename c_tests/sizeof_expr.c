@@ -0,0 +1,23 @@
+// `sizeof` on expressions, which are never evaluated, & unary plus
+
+struct pt { int x; int y; };
+
+int len(int a[10]) { return sizeof a; }
+
+int main() {
+  int arr[5];
+  struct pt p;
+  char c = 'A';
+  int n = 0;
+  putchar('0' + sizeof arr / sizeof arr[0]);
+  putchar('0' + sizeof(arr) / sizeof(arr[0]));
+  putchar('0' + sizeof p);
+  putchar('0' + sizeof(p.y));
+  putchar('0' + len(arr));
+  putchar('0' + sizeof n++); // not evaluated
+  putchar('0' + n);
+  putchar(+c);
+  putchar('0' + -+-3);
+  putchar('0' + sizeof(int[2]) + sizeof +c);
+  putchar('\n');
+}
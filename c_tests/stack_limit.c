@@ -0,0 +1,12 @@
+// Each call's frame is small, but the arguments pushed on top of it for the
+// next call are not, so `-fstack-limit=` has to count those too
+int sum(int depth, long long a, long long b, long long c) {
+  if (depth == 0) {
+    return 1;
+  }
+  return sum(depth - 1, a, b, c) + 1;
+}
+
+int main() {
+  return sum(8, 1, 2, 3);
+}
@@ -0,0 +1,17 @@
+// A local only hides a `static` global within its own block
+static int count = 5;
+
+int f(int step) {
+  {
+    int count = 1;
+    count++;
+  }
+  for (int count = 0; count < 3; count++) {
+    step++;
+  }
+  return count + step;
+}
+
+int main() {
+  return f(1);
+}
@@ -0,0 +1,48 @@
+// Passes & returns values larger than one word.
+
+struct point {
+  int x;
+  int y;
+};
+
+struct line {
+  struct point from;
+  struct point to;
+};
+
+struct point make(int x, int y) {
+  struct point p = {x, y};
+  return p;
+}
+
+struct point add(struct point a, struct point b) {
+  struct point sum = {a.x + b.x, a.y + b.y};
+  return sum;
+}
+
+int length(struct line l) {
+  return (l.to.x - l.from.x) + (l.to.y - l.from.y);
+}
+
+void shift(struct point *p, int d) {
+  p->x = p->x + d;
+  p->y = p->y + d;
+}
+
+int main() {
+  struct point p = add(make('0', '1'), make(1, 1));
+  putchar(p.x);
+  putchar(p.y);
+
+  struct line l = {{1, 2}, make(4, 7)};
+  putchar('0' + length(l));
+
+  shift(&l.from, 2);
+  putchar('0' + l.from.x);
+
+  struct point ps[3];
+  ps[2] = make('a', 'b');
+  ps[0] = ps[2];
+  putchar(ps[0].y);
+  putchar(make('x', 'y').x);
+}
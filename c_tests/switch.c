@@ -0,0 +1,32 @@
+// Constant expressions as array bounds & case labels.
+
+enum color { RED, GREEN = 5, BLUE };
+
+int main() {
+  putchar('0' + sizeof(char[(3 > 2) ? 10 : 1]) - 10);
+  putchar('0' + sizeof(int[2 * BLUE - 1]) - 2);
+
+  for (int i = 0; i < 8; i++) {
+    switch (i) {
+    case RED:
+      putchar('r');
+      break;
+    case GREEN:
+    case BLUE:
+      putchar('g' + i - GREEN);
+      break;
+    case 1 << 1:
+      putchar('\t' == 9 ? 't' : 'f');
+    case -1 + 4:
+      putchar('\x41');
+      break;
+    default:
+      putchar('.');
+    }
+  }
+
+  switch (BLUE) {
+  default:
+    putchar('\n');
+  }
+}
@@ -0,0 +1,27 @@
+// Struct tags belong to the block they are declared in, & hide those outside
+
+struct node { int v; struct node *next; };
+
+int f() {
+  struct S { int a; int b; } s;
+  s.a = 1;
+  s.b = 2;
+  return s.a + s.b;
+}
+
+int main() {
+  struct S { int z; } t;
+  t.z = 4;
+
+  struct node n2 = { 2, 0 };
+  struct node n1 = { 1, &n2 };
+  {
+    struct node { int w; } inner;
+    inner.w = 10;
+    t.z = t.z + inner.w;
+  }
+
+  // The file's `node` is back in sight
+  struct node *p = n1.next;
+  return f() + t.z + p->v;
+}
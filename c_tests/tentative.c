@@ -0,0 +1,11 @@
+// A tentative definition defines what an earlier `extern` declared
+extern int x;
+int x;
+extern int y;
+int y = 4;
+extern int y;
+
+int main() {
+  x = 3;
+  return x + y;
+}
@@ -0,0 +1,11 @@
+// A declaration without a parameter list leaves the arguments to the definition
+
+int add();
+
+int main() {
+  return add(3, 4);
+}
+
+int add(int a, int b) {
+  return a + b;
+}
@@ -0,0 +1,14 @@
+// no warnings
+// Naming a variable in an array size uses it
+int count(long n) {
+  char bytes[sizeof n];
+  bytes[0] = 1;
+  return sizeof bytes;
+}
+
+int main() {
+  int primes[4] = {2, 3, 5, 7};
+  int copy[sizeof primes / sizeof primes[0]];
+  copy[3] = primes[3];
+  return copy[3] + count(1);
+}
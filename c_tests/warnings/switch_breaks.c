@@ -0,0 +1,13 @@
+// warning: `f` can reach its end without returning a value
+int f(int x) {
+  switch (x) {
+  case 1:
+    break;
+  default:
+    return 2;
+  }
+}
+
+int main() {
+  return f(2);
+}
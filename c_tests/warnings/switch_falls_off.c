@@ -0,0 +1,12 @@
+// warning: `f` can reach its end without returning a value
+// Without a `default`, other values skip the switch
+int f(int x) {
+  switch (x) {
+  case 1:
+    return 1;
+  }
+}
+
+int main() {
+  return f(1);
+}
@@ -0,0 +1,14 @@
+// no warnings
+// A switch with a `default` that returns can't fall off the end
+int sign(int x) {
+  switch (x > 0) {
+  case 1:
+    return 1;
+  default:
+    return x < 0 ? -1 : 0;
+  }
+}
+
+int main() {
+  return sign(3) - 1;
+}
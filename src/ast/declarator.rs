@@ -7,13 +7,13 @@ pub enum Declarator {
     Abstract,
     Var(Ident),
     Deref(usize, Box<Self>),
-    Call(Box<Self>, Vec<ParamDecl>),
-    Index(Box<Self>, Word),
+    Call(Box<Self>, Option<Vec<ParamDecl>>), // No parameter list, as in `f()`
+    Index(Box<Self>, Word, Vec<Ident>),      // & the variables its size names
     Unsized(Box<Self>),
 }
 
 impl ASTNode for Declarator {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use Declarator::*;
         parser_rule! {
             pair:
@@ -46,7 +46,7 @@ impl ASTNode for Declarator {
                     let rule = base_pair.as_rule();
 
                     let mut base = match rule {
-                        declarator | abstract_declarator => Self::parse(exts.remove(0)),
+                        declarator | abstract_declarator => Self::parse(exts.remove(0))?,
                         IDENTIFIER => Var((exts.remove(0).as_str().into())),
                         brackets | const_sized | sized | params => Abstract,
                         r => unreachable!("{:?}", r),
@@ -56,15 +56,37 @@ impl ASTNode for Declarator {
                         base = match ext.as_rule() {
                             brackets | typequal => Unsized(base.into()),
                             const_sized | sized => {
+                                let span = ext.as_span();
                                 let size_expr = ext.into_inner().last().unwrap();
-                                let size_expr = Expr::parse(size_expr);
-                                if let Some(size) = size_expr.const_arithmetic_expr() {
-                                    Index(base.into(), size as Word)
-                                } else {
-                                    // VLA
-                                    base.pointed()
+                                let size_expr = Expr::parse(size_expr)?;
+                                let mut vars = vec![];
+                                size_expr.visit(&mut |e| {
+                                    if let Expr::Var(v) = e {
+                                        vars.push(v.clone());
+                                    }
+                                });
+                                match size_expr.const_eval(None) {
+                                    Some((size, _)) if size >= 0 => {
+                                        Index(base.into(), size as Word, vars)
+                                    }
+                                    Some(_) => {
+                                        return Err(Diagnostic::at(span, "array size is negative"));
+                                    }
+                                    // No VLAs
+                                    None => {
+                                        let msg = if size_expr.divides_by_zero() {
+                                            "division by zero in array size"
+                                        } else {
+                                            "array size is not an integer constant expression"
+                                        };
+                                        return Err(Diagnostic::at(span, msg));
+                                    }
                                 }
                              },
+                             // `f()` says nothing about the parameters
+                             params if ext.clone().into_inner().next().is_none() => {
+                                 Call(Box::new(base), None)
+                             }
                              params => {
                                  let param_list = ext.into_inner();
                                  let mut param_vec = vec![];
@@ -72,18 +94,25 @@ impl ASTNode for Declarator {
                                  for param in param_list {
                                      let mut pairs = param.into_inner();
                                      // Type of parameter
-                                     let base_ty = DType::parse(pairs.next().unwrap());
+                                     let base_ty = DType::parse(pairs.next().unwrap())?;
                                      // declarator
-                                     let mut param = pairs.next().map(Self::parse).unwrap_or(Abstract);
+                                     let param = pairs.next().map(Self::parse).transpose()?;
+                                     let param = param.unwrap_or(Abstract);
 
-                                     let ty = param.set_type(base_ty);
+                                     // `T a[N]` & `T f()` parameters are pointers
+                                     let ty = param.set_type(base_ty).decayed();
                                      let ident = param.get_name();
                                      let param_decl = (ty, ident);
 
                                      param_vec.push(param_decl);
                                  }
 
-                                 Call(Box::new(base), param_vec)
+                                 // `f(void)` takes no parameters
+                                 if let [(DType::Void, None)] = &param_vec[..] {
+                                     param_vec.clear();
+                                 }
+
+                                 Call(Box::new(base), Some(param_vec))
                              }
                              r => unreachable!("{:?}", r),
                         }
@@ -100,7 +129,32 @@ impl Declarator {
         match self {
             Abstract => None,
             Var(v) => Some(v.clone()),
-            Deref(_, d) | Index(d, _) | Call(d, _) | Unsized(d) => d.get_name(),
+            Deref(_, d) | Index(d, _, _) | Call(d, _) | Unsized(d) => d.get_name(),
+        }
+    }
+
+    // Gives the outermost dimension of an array of unknown size, e.g. `T[]`,
+    // the size its initializer implies
+    pub fn sized(&self, n: Word) -> Self {
+        use Declarator::*;
+        match self {
+            Unsized(d) if matches!(**d, Abstract | Var(_)) => Index(d.clone(), n, vec![]),
+            Abstract | Var(_) => self.clone(),
+            Deref(k, d) => Deref(*k, d.sized(n).into()),
+            Call(d, ps) => Call(d.sized(n).into(), ps.clone()),
+            Index(d, s, vs) => Index(d.sized(n).into(), *s, vs.clone()),
+            Unsized(d) => Unsized(d.sized(n).into()),
+        }
+    }
+
+    // Whether this declares a function without a parameter list, which may
+    // then be called with any arguments
+    pub fn is_unprototyped(&self) -> bool {
+        use Declarator::*;
+        match self {
+            Call(d, ps) if matches!(**d, Var(_)) => ps.is_none(),
+            Deref(_, d) | Index(d, _, _) | Call(d, _) | Unsized(d) => d.is_unprototyped(),
+            Abstract | Var(_) => false,
         }
     }
 
@@ -116,7 +170,17 @@ impl Declarator {
         use Declarator::*;
         match self {
             Abstract | Var(_) => *self = Var(ident),
-            Deref(_, d) | Index(d, _) | Call(d, _) | Unsized(d) => d.set_name(ident),
+            Deref(_, d) | Index(d, _, _) | Call(d, _) | Unsized(d) => d.set_name(ident),
+        }
+    }
+
+    // Variables named in array sizes, as by `sizeof`, which count as uses
+    pub fn size_vars(&self) -> Vec<Ident> {
+        use Declarator::*;
+        match self {
+            Abstract | Var(_) => vec![],
+            Index(d, _, vs) => [d.size_vars(), vs.clone()].concat(),
+            Deref(_, d) | Call(d, _) | Unsized(d) => d.size_vars(),
         }
     }
 
@@ -125,13 +189,17 @@ impl Declarator {
         use Declarator::*;
         match self {
             Abstract | Var(_) => decl_type,
-            Deref(_, d) | Unsized(d) => d.set_type(decl_type.pointer()),
+            Deref(n, d) => {
+                let ty = (0..*n).fold(decl_type, |ty, _| ty.pointer());
+                d.set_type(ty)
+            }
+            Unsized(d) => d.set_type(decl_type.pointer()),
             Call(d, ps) => {
-                let params = ps.iter().map(|(t, _)| t).cloned().collect();
+                let params = ps.iter().flatten().map(|(t, _)| t).cloned().collect();
                 let func = Function(params, decl_type.into());
                 d.set_type(func)
             }
-            Index(d, s) => d.set_type(Array(*s, decl_type.into())),
+            Index(d, s, _) => d.set_type(Array(*s, decl_type.into())),
         }
     }
 }
@@ -1,53 +1,111 @@
 use super::*;
 
+// Storage class, as far as linkage is concerned
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Storage {
+    #[default]
+    Plain,
+    Static,
+    Extern,
+}
+
+impl ASTNode for Storage {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
+        let mut storage = Storage::Plain;
+        for spec in pair.into_inner() {
+            match spec.as_str() {
+                "static" => storage = Storage::Static,
+                "extern" => storage = Storage::Extern,
+                _ => (),
+            }
+        }
+        Ok(storage)
+    }
+}
+
 #[derive(Debug)]
 pub enum Defn {
     Struct(DType, Option<Ident>),
     Union(DType, Option<Ident>),
     Enum(DType, Option<Ident>),
     TypeDef(DType, Vec<Declarator>),
-    Vars(bool, DType, Vec<(Declarator, Option<Expr>)>), // bool indicates whether decl is static
-    FDef(Ident, DType, Vec<ParamDecl>, Box<Stmt>),
+    Vars(Storage, DType, Vec<(Declarator, Option<Expr>)>),
+    FDef(Storage, Ident, DType, Vec<ParamDecl>, Box<Stmt>),
+    Include(String), // Name of a built-in header
 }
 
 impl ASTNode for Defn {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use Declarator::*;
         use Defn::*;
         parser_rule! {
             pair:
 
             function_definition
-                [i:(), ty, d, body] -> {
+                [s:Storage, ty, d, .. body] -> {
                     let Call(n, args) = d else {
                         unreachable!()
                     };
                     let Var(name) = *n else {
                         unreachable!()
                     };
+                    let args = args.unwrap_or_default();
+                    let body = scope(false, || {
+                        for (ty, p) in &args {
+                            if let Some(p) = p {
+                                declare_var(p, ty.clone());
+                            }
+                        }
+                        Stmt::parse(body.next().unwrap())
+                    })?;
 
-                    FDef(name, ty, args, Box::new(body))
+                    // The body's span points at the function as a whole
+                    let body = Stmt::Spanned(pair.as_span().into(), Box::new(body));
+                    FDef(s, name, ty, args, Box::new(body))
                 };
 
             external_declaration
                 [e] -> e;
 
+            include
+                [h:String] -> Include(h);
+
             declaration
                 [t] -> t;
-                [s:(), d:Self] -> {
-                    d.make_static();
-                    d
-                };
 
             typedef
                 [ty, .. ds] -> {
-                    let ds = ds.map(Declarator::parse).collect();
+                    let ds = ds.map(Declarator::parse).collect::<Result<_>>()?;
                     TypeDef(ty, ds)
                 };
 
             vdecl
-                [i:(), ty, ds:Self] -> {
+                // Only declares a tag, e.g. `struct point { ... };`
+                [s:Storage, ty] -> Vars(s, ty, vec![]);
+                [s:Storage, ty: DType, ds:Self] -> {
+                    // e.g. `static const int`
+                    let specs = pair.clone().into_inner().next().unwrap();
+                    let ty = match specs.into_inner().any(|s| s.as_str() == "const") {
+                        true => ty.with_const_base(),
+                        false => ty,
+                    };
+                    if let Vars(_, _, defs) = &mut ds {
+                        for (d, _) in defs {
+                            let Some(v) = d.get_name() else { continue };
+                            let dty = d.set_type(ty.clone());
+                            // `static` locals get renamed, to live at file scope
+                            if s == Storage::Static
+                                && in_block()
+                                && !matches!(dty, DType::Function(_, _))
+                            {
+                                d.set_name(declare_static(&v, dty));
+                            } else {
+                                declare_var(&v, dty);
+                            }
+                        }
+                    }
                     ds.change_base_ty(ty);
+                    ds.set_storage(s);
                     ds
                 };
 
@@ -60,21 +118,20 @@ impl ASTNode for Defn {
 
             init_declarator
                 [d:Declarator] -> {
-                    Vars(false, DType::Void, vec![(d, None)])
+                    Vars(Storage::Plain, DType::Void, vec![(d, None)])
                 };
                 [d:Declarator, i] -> {
-                    Vars(false, DType::Void, vec![(d, Some(i))])
+                    Vars(Storage::Plain, DType::Void, vec![(d, Some(i))])
                 };
         }
     }
 }
 
 impl Defn {
-    fn make_static(&mut self) {
+    fn set_storage(&mut self, storage: Storage) {
         use Defn::*;
         match self {
-            Vars(s, _, _) => *s = true,
-            FDef(_, _, _, _) => (),
+            Vars(s, _, _) | FDef(s, _, _, _, _) => *s = storage,
             _ => unreachable!(),
         }
     }
@@ -83,7 +140,7 @@ impl Defn {
         use Defn::*;
         match self {
             Vars(_, d, _) => *d = ty,
-            FDef(_, t, _, _) => *t = ty,
+            FDef(_, _, t, _, _) => *t = ty,
             _ => unreachable!(),
         }
     }
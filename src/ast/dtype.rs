@@ -1,8 +1,13 @@
 use super::*;
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
 pub enum DType {
+    #[default]
     Void,
+    Bool,
     U8,
     S8,
     U16,
@@ -17,10 +22,133 @@ pub enum DType {
     Array(Word, Box<DType>),
     Unsized(Box<DType>),
     Function(Vec<DType>, Box<DType>),
+    Struct(Ident),        // Struct or union, by tag
+    ReadOnly(Box<DType>), // `const`
+}
+
+// Name, type & bit-field width of a member. Unnamed bit-fields only pad.
+pub type Member = (Ident, DType, Option<Word>);
+
+// Members of a struct or union
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Aggregate {
+    pub union: bool,
+    pub members: Vec<Member>,
+}
+
+// Where a member lives: its offset in words, & the shift & width of a bit-field
+pub type Slot = (Word, Option<(Word, Word)>);
+
+thread_local! {
+    // Definitions of every struct & union seen by the parser. Types refer to
+    // them by key only, so that they may point to themselves.
+    static AGGREGATES: RefCell<HashMap<Ident, Aggregate>> = RefCell::default();
+
+    // What each enclosing block declares, innermost last
+    static SCOPES: RefCell<Vec<Scope>> = RefCell::default();
+    static NEXT_KEY: Cell<usize> = Cell::default();
+}
+
+#[derive(Default)]
+struct Scope {
+    // Keys of the tags, so that each declaration gets its own
+    tags: HashMap<Ident, Ident>,
+    // Variables & enumeration constants, which share a namespace
+    names: HashMap<Ident, Name>,
+}
+
+enum Name {
+    Var(Ident, DType), // What uses of it are renamed to, & its type
+    Enumerator(i128),
+}
+
+// Parses `f` in a new block, whose tags & variables are gone once it ends.
+// Translation units are parsed with `new_unit` set, so they can't see each
+// other's.
+pub fn scope<T>(new_unit: bool, f: impl FnOnce() -> T) -> T {
+    let outer = SCOPES.with_borrow_mut(|s| match new_unit {
+        true => std::mem::take(s),
+        false => vec![],
+    });
+    SCOPES.with_borrow_mut(|s| s.push(Scope::default()));
+    let result = f();
+    SCOPES.with_borrow_mut(|s| match new_unit {
+        true => *s = outer,
+        false => drop(s.pop()),
+    });
+    result
+}
+
+fn innermost<T>(f: impl FnOnce(&mut Scope) -> T) -> T {
+    SCOPES.with_borrow_mut(|s| {
+        if s.is_empty() {
+            s.push(Scope::default());
+        }
+        f(s.last_mut().unwrap())
+    })
+}
+
+// A name of its own for `name`, which is `name` plus `#n`
+fn unique_key(name: &str) -> Ident {
+    format!("{}#{}", name, NEXT_KEY.replace(NEXT_KEY.get() + 1))
+}
+
+// Whether the parser is inside a function, rather than at file scope
+pub fn in_block() -> bool {
+    SCOPES.with_borrow(|s| s.len() > 1)
+}
+
+// Declares `tag` in the innermost block, returning its key
+fn declare_tag(tag: &str) -> Ident {
+    let key = unique_key(tag);
+    innermost(|s| s.tags.insert(tag.into(), key.clone()));
+    key
+}
+
+pub fn declare_var(v: &str, ty: DType) {
+    innermost(|s| s.names.insert(v.into(), Name::Var(v.into(), ty)));
+}
+
+// Declares a `static` local, which lives at file scope under the key returned
+pub fn declare_static(v: &str, ty: DType) -> Ident {
+    let key = unique_key(v);
+    innermost(|s| {
+        s.names.insert(v.into(), Name::Var(key.clone(), ty.clone()));
+        s.names.insert(key.clone(), Name::Var(key.clone(), ty));
+    });
+    key
+}
+
+// What a use of the variable `v` refers to
+pub fn var_key(v: &str) -> Ident {
+    lookup(v, |n| match n {
+        Name::Var(key, _) => Some(key.clone()),
+        Name::Enumerator(_) => None,
+    })
+    .unwrap_or_else(|| v.into())
+}
+
+// What the parser sees `name` name, from the innermost block declaring it
+fn lookup<T>(name: &str, f: impl FnOnce(&Name) -> Option<T>) -> Option<T> {
+    SCOPES.with_borrow(|s| s.iter().rev().find_map(|s| s.names.get(name)).and_then(f))
+}
+
+pub fn var_type(v: &str) -> Option<DType> {
+    lookup(v, |n| match n {
+        Name::Var(_, ty) => Some(ty.clone()),
+        Name::Enumerator(_) => None,
+    })
+}
+
+pub fn enum_value(name: &str) -> Option<i128> {
+    lookup(name, |n| match n {
+        Name::Enumerator(v) => Some(*v),
+        Name::Var(_, _) => None,
+    })
 }
 
 impl ASTNode for DType {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use DType::*;
         parser_rule! {
             pair :
@@ -33,7 +161,7 @@ impl ASTNode for DType {
 
             specifier_qualifier_list
                 [.. sql,] -> {
-                    let specs = sql.filter(|s| s.as_rule() == type_specifier);
+                    let specs = sql.clone().filter(|s| s.as_rule() == type_specifier);
 
                     let mut long = false;
                     let mut signed = true;
@@ -42,19 +170,22 @@ impl ASTNode for DType {
                     for spec in specs {
                         match spec.as_str() {
                             "void" => ty = Void,
+                            "_Bool" | "bool" => ty = Bool,
                             "unsigned" => signed = false,
                             "signed" => signed = true,
                             "char" => ty = S8,
                             "short" => ty = S16,
                             "int" => ty = S16,
+                            "long" if long => ty = S64,
                             "long" => {
                                 long = true;
                                 ty = S32;
                             }
-                            "long" if long => ty = S64,
-                            "float" => ty = Float,
-                            "double" => ty = Double,
-                            _ => unreachable!(),
+                            "float" | "double" => {
+                                let msg = format!("unsupported type `{}`", spec.as_str());
+                                return Err(Diagnostic::at(spec.as_span(), msg));
+                            }
+                            _ => ty = DType::parse(spec.into_inner().next().unwrap())?,
                         }
                     }
 
@@ -62,13 +193,114 @@ impl ASTNode for DType {
                         ty = ty.make_unsigned();
                     }
 
+                    if sql.any(|q| q.as_str() == "const") {
+                        ty = ty.constant();
+                    }
+
                     ty
                 };
+
+            struct_or_union_specifier
+                [.. parts,] -> {
+                    let union = parts.next().unwrap().as_str() == "union";
+                    let (tag, list) = match (parts.next(), parts.next()) {
+                        (Some(t), l) if t.as_rule() == IDENTIFIER => (t.as_str().into(), l),
+                        (l, _) => (format!("<anonymous@{}>", pair.as_span().start()), l),
+                    };
+
+                    let visible = SCOPES.with_borrow(|s| {
+                        let mut scopes = s.iter().rev();
+                        match list {
+                            // A definition only completes a tag of its own block
+                            Some(_) => scopes.next().and_then(|s| s.tags.get(&tag)).cloned(),
+                            None => scopes.find_map(|s| s.tags.get(&tag)).cloned(),
+                        }
+                    });
+
+                    // Declared before the members, which may point to it
+                    let key = visible.unwrap_or_else(|| declare_tag(&tag));
+
+                    if let Some(list) = list {
+                        let span = pair.as_span();
+                        if AGGREGATES.with_borrow(|a| a.contains_key(&key)) {
+                            let kind = if union { "union" } else { "struct" };
+                            let msg = format!("redefinition of `{} {}`", kind, tag);
+                            return Err(Diagnostic::at(span, msg));
+                        }
+
+                        let members = Aggregate::parse_members(list)?;
+                        let aggregate = Aggregate { union, members };
+                        AGGREGATES.with_borrow_mut(|a| a.insert(key.clone(), aggregate));
+                    }
+
+                    Struct(key)
+                };
+
+            enum_specifier
+                [.. parts,] -> {
+                    let Some(list) = parts.find(|p| p.as_rule() == enumerator_list) else {
+                        return Ok(S16);
+                    };
+
+                    let mut next = 0;
+                    for e in list.into_inner() {
+                        let mut e = e.into_inner();
+                        let name = e.next().unwrap().as_str();
+                        if let Some(v) = e.next() {
+                            let span = v.as_span();
+                            let Some((v, _)) = Expr::parse(v)?.const_eval(None) else {
+                                return Err(Diagnostic::at(
+                                    span,
+                                    "enumerator value is not an integer constant",
+                                ));
+                            };
+                            next = v;
+                        }
+
+                        innermost(|s| s.names.insert(name.into(), Name::Enumerator(next)));
+                        next += 1;
+                    }
+
+                    // Enumerations are ints
+                    S16
+                };
         }
     }
 }
 
 impl DType {
+    pub fn constant(self) -> Self {
+        match self {
+            DType::ReadOnly(_) => self,
+            ty => DType::ReadOnly(ty.into()),
+        }
+    }
+
+    // Makes the innermost type `const`, as a `const` in front of a whole type
+    // name does, e.g. `const int *` points to a `const int`
+    pub fn with_const_base(self) -> Self {
+        use DType::*;
+        match self {
+            Pointer(n, b) => Pointer(n, b.with_const_base().into()),
+            Array(n, b) => Array(n, b.with_const_base().into()),
+            Unsized(b) => Unsized(b.with_const_base().into()),
+            Function(ps, r) => Function(ps, r.with_const_base().into()),
+            ty => ty.constant(),
+        }
+    }
+
+    pub fn is_const(&self) -> bool {
+        matches!(self, DType::ReadOnly(_))
+    }
+
+    // The type without its `const`, which only matters to objects
+    pub fn unqualified(&self) -> &Self {
+        match self {
+            DType::ReadOnly(ty) => ty,
+            ty => ty,
+        }
+    }
+
     pub fn pointer(self) -> Self {
         use DType::*;
         match self {
@@ -77,6 +309,169 @@ impl DType {
         }
     }
 
+    // Type of the object this points to
+    pub fn deref(&self) -> Option<Self> {
+        use DType::*;
+        match self.unqualified() {
+            Pointer(1, b) => Some((**b).clone()),
+            Pointer(n, b) => Some(Pointer(n - 1, b.clone())),
+            Array(_, b) | Unsized(b) => Some((**b).clone()),
+            _ => None,
+        }
+    }
+
+    // Arrays & functions are converted to pointers when used as values
+    pub fn decayed(&self) -> Self {
+        use DType::*;
+        match self.unqualified() {
+            Array(_, b) | Unsized(b) => (**b).clone().pointer(),
+            Function(_, _) => self.clone().pointer(),
+            ty => ty.clone(),
+        }
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        use DType::*;
+        matches!(
+            self.unqualified(),
+            Bool | U8 | S8 | U16 | S16 | U32 | S32 | U64 | S64 | Float | Double
+        )
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self.decayed(), DType::Pointer(_, _))
+    }
+
+    // Type both operands are converted to by arithmetic operators
+    pub fn common(&self, other: &Self) -> Self {
+        use DType::*;
+        let rank = |t: &Self| match t {
+            Double => 5,
+            Float => 4,
+            U64 | S64 => 3,
+            U32 | S32 => 2,
+            _ => 1,
+        };
+
+        let promote = |t: &Self| match t.unqualified() {
+            Bool | U8 | S8 | S16 => S16,
+            t => t.clone(),
+        };
+
+        let (a, b) = (promote(self), promote(other));
+        match rank(&a).cmp(&rank(&b)) {
+            std::cmp::Ordering::Less => b,
+            std::cmp::Ordering::Greater => a,
+            std::cmp::Ordering::Equal if a.is_signed() => b,
+            std::cmp::Ordering::Equal => a,
+        }
+    }
+
+    // Width of a number of this type
+    pub fn bits(&self) -> u32 {
+        use DType::*;
+        match self.unqualified() {
+            Bool => 1,
+            U8 | S8 => 8,
+            U32 | S32 | Float => 32,
+            U64 | S64 | Double => 64,
+            _ => 16,
+        }
+    }
+
+    // Converts an integer to this type, wrapping it like the target would
+    pub fn wrap(&self, v: i128) -> i128 {
+        if *self.unqualified() == DType::Bool {
+            return (v != 0) as i128;
+        }
+
+        let bits = self.bits();
+        let v = v & ((1 << bits) - 1);
+        if self.is_signed() && v >> (bits - 1) == 1 {
+            v - (1 << bits)
+        } else {
+            v
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        use DType::*;
+        matches!(self.unqualified(), S8 | S16 | S32 | S64 | Float | Double)
+    }
+
+    // Whether a value of type `other` may be implicitly converted to this
+    // type, e.g. when passed as an argument.
+    pub fn accepts(&self, other: &Self) -> bool {
+        use DType::*;
+        let (to, from) = (self.decayed(), other.decayed());
+
+        if to.is_arithmetic() && from.is_arithmetic() {
+            return true;
+        }
+
+        // Pointers may gain a `const`, but not lose one
+        match (to.deref(), from.deref()) {
+            (Some(t), Some(_)) | (Some(_), Some(t)) if *t.unqualified() == Void => true,
+            (Some(t), Some(f)) => {
+                t.unqualified().compatible(f.unqualified()) && (t.is_const() || !f.is_const())
+            }
+            _ => to.compatible(&from),
+        }
+    }
+
+    // Whether two types are the same, where structs & unions declared in
+    // different blocks or units are told apart by their tags only
+    pub fn compatible(&self, other: &Self) -> bool {
+        self.untagged() == other.untagged()
+    }
+
+    fn untagged(&self) -> Self {
+        use DType::*;
+        match self {
+            Pointer(n, b) => Pointer(*n, b.untagged().into()),
+            Array(n, b) => Array(*n, b.untagged().into()),
+            Unsized(b) => Unsized(b.untagged().into()),
+            Function(ps, r) => {
+                Function(ps.iter().map(Self::untagged).collect(), r.untagged().into())
+            }
+            Struct(key) => Struct(key.split('#').next().unwrap().into()),
+            ReadOnly(b) => ReadOnly(b.untagged().into()),
+            ty => ty.clone(),
+        }
+    }
+
+    pub fn aggregate(&self) -> Option<Aggregate> {
+        let DType::Struct(tag) = self.unqualified() else {
+            return None;
+        };
+        AGGREGATES.with_borrow(|a| a.get(tag).cloned())
+    }
+
+    // Offset & type of a struct or union member
+    pub fn member(&self, name: &str) -> Option<(Word, DType)> {
+        let (ty, (offset, _)) = self.slot(name)?;
+        // Members of a `const` struct are `const` too
+        let ty = if self.is_const() { ty.constant() } else { ty };
+        Some((offset, ty))
+    }
+
+    // Shift & width of a bit-field member
+    pub fn bit_field(&self, name: &str) -> Option<(Word, Word)> {
+        self.slot(name)?.1.1
+    }
+
+    fn slot(&self, name: &str) -> Option<(DType, Slot)> {
+        let aggregate = self.aggregate()?;
+        let (slots, _) = aggregate.layout();
+
+        aggregate
+            .members
+            .into_iter()
+            .zip(slots)
+            .find(|((m, _, _), _)| m == name)
+            .map(|((_, ty, _), slot)| (ty, slot))
+    }
+
     fn make_unsigned(&self) -> Self {
         use DType::*;
         match self {
@@ -88,19 +483,154 @@ impl DType {
         }
     }
 
+    // # of words an object takes. Wider integers are split into words, the
+    // lowest first.
     pub fn size(&self) -> Word {
         use DType::*;
         match self {
+            ReadOnly(ty) => ty.size(),
             Void => 0,
-            U8 | S8 => 1,
+            Bool | U8 | S8 => 1,
             U16 | S16 => 1,
-            U32 | S32 => 4,
-            U64 | S64 => 8,
-            Float => 4,
-            Double => 8,
+            U32 | S32 | Float => 2,
+            U64 | S64 | Double => 4,
             Pointer(_, _) | Unsized(_) => 1,
             Array(n, dtype) => n * dtype.size(),
             Function(_, _) => unreachable!(),
+            Struct(_) => match self.aggregate() {
+                Some(aggregate) => aggregate.layout().1,
+                None => 0, // Incomplete
+            },
         }
     }
 }
+
+impl std::fmt::Display for DType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DType::*;
+        match self {
+            Void => write!(f, "void"),
+            Bool => write!(f, "_Bool"),
+            U8 => write!(f, "unsigned char"),
+            S8 => write!(f, "char"),
+            U16 => write!(f, "unsigned int"),
+            S16 => write!(f, "int"),
+            U32 => write!(f, "unsigned long"),
+            S32 => write!(f, "long"),
+            U64 => write!(f, "unsigned long long"),
+            S64 => write!(f, "long long"),
+            Float => write!(f, "float"),
+            Double => write!(f, "double"),
+            Pointer(n, b) => write!(f, "{} {}", b, "*".repeat(*n)),
+            Array(n, b) => write!(f, "{}[{}]", b, n),
+            Unsized(b) => write!(f, "{}[]", b),
+            Function(ps, r) => {
+                let ps = ps.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                write!(f, "{} ({})", r, ps.join(", "))
+            }
+            Struct(key) => {
+                let tag = key.split('#').next().unwrap();
+                match self.aggregate() {
+                    Some(Aggregate { union: true, .. }) => write!(f, "union {}", tag),
+                    _ => write!(f, "struct {}", tag),
+                }
+            }
+            ReadOnly(b) => write!(f, "const {}", b),
+        }
+    }
+}
+
+impl Aggregate {
+    // Where each member goes, & the total size. Bit-fields are packed into a
+    // cell until the next one doesn't fit, or a width of 0 closes it.
+    pub fn layout(&self) -> (Vec<Slot>, Word) {
+        let mut slots = vec![];
+        let mut size = 0;
+        let mut used = None; // Bits taken in the last cell, if it holds bit-fields
+
+        for (_, ty, width) in &self.members {
+            if self.union {
+                slots.push((0, width.map(|w| (0, w))));
+                size = size.max(width.map_or(ty.size(), |_| 1));
+                continue;
+            }
+
+            match width {
+                None | Some(0) => {
+                    slots.push((size, None));
+                    size += if width.is_some() { 0 } else { ty.size() };
+                    used = None;
+                }
+                Some(w) => match used {
+                    Some(bits) if bits + w <= Word::BITS as Word => {
+                        slots.push((size - 1, Some((bits, *w))));
+                        used = Some(bits + w);
+                    }
+                    _ => {
+                        slots.push((size, Some((0, *w))));
+                        size += 1;
+                        used = Some(*w);
+                    }
+                },
+            }
+        }
+
+        (slots, size)
+    }
+
+    fn parse_members(list: Pair<Rule>) -> Result<Vec<Member>> {
+        let mut members = vec![];
+
+        for decl in list.into_inner() {
+            let mut pairs = decl.into_inner();
+            let base_ty = DType::parse(pairs.next().unwrap())?;
+
+            for declarator in pairs.next().unwrap().into_inner() {
+                let span = declarator.as_span();
+                let mut name = Ident::new();
+                let mut ty = base_ty.clone();
+                let mut width = None;
+
+                for part in declarator.into_inner() {
+                    match part.as_rule() {
+                        Rule::declarator => {
+                            let d = Declarator::parse(part)?;
+                            name = d.get_name().unwrap();
+                            ty = d.set_type(base_ty.clone());
+                        }
+                        _ => {
+                            let Some(w) = Expr::parse(part)?.const_arithmetic_expr() else {
+                                return Err(Diagnostic::at(
+                                    span,
+                                    "bit-field width is not an integer constant",
+                                ));
+                            };
+                            width = Some(w);
+                        }
+                    }
+                }
+
+                let Some(w) = width else {
+                    members.push((name, ty, None));
+                    continue;
+                };
+
+                if !ty.is_arithmetic() || matches!(ty, DType::Float | DType::Double) {
+                    let msg = format!("bit-field `{}` has non-integer type `{}`", name, ty);
+                    return Err(Diagnostic::at(span, msg));
+                }
+                if w > ty.bits().min(Word::BITS) as u64 {
+                    let msg = format!("width of bit-field `{}` exceeds its type `{}`", name, ty);
+                    return Err(Diagnostic::at(span, msg));
+                }
+                if w == 0 && !name.is_empty() {
+                    return Err(Diagnostic::at(span, "named bit-field has a width of 0"));
+                }
+
+                members.push((name, ty, Some(w as Word)));
+            }
+        }
+
+        Ok(members)
+    }
+}
@@ -18,26 +18,59 @@ pub enum Expr {
     Arrow(Box<Expr>, Ident),
     Inc(Box<Expr>),
     Dec(Box<Expr>),
+    Intrinsic(Ident, String, Vec<Expr>),
+    CompoundLit(Ident, DType, Box<Expr>), // An unnamed object, & its initializer
 }
 
 impl ASTNode for Expr {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use Expr::*;
         let s = pair.as_str();
         parser_rule! {
             pair:
 
-            IDENTIFIER [] -> Var(s.into());
+            IDENTIFIER [] -> match enum_value(s) {
+                Some(v) if v < 0 => Unary(MonOp::Negate, Const(v.unsigned_abs() as usize).into()),
+                Some(v) => Const(v as usize),
+                None => Var(var_key(s)),
+            };
             CONSTANT [e] -> e;
             decimal [] -> Const(s.parse::<usize>().unwrap());
             octal [] -> Const(usize::from_str_radix(s, 8).unwrap());
             hexadecimal [] -> Const(usize::from_str_radix(s, 16).unwrap());
-            character [] -> Const(s.chars().nth(1).unwrap() as usize);
-            string_literal [] -> todo!();
+            character [] -> Const(char_value(&s[1..s.len() - 1]));
+            string_literal [] -> {
+                return Err(Diagnostic::at(pair.as_span(), "string literals are not supported yet"));
+            };
 
             primary_expr
                 [e] -> e;
 
+            intrinsic
+                [name: String, .. args] -> {
+                    let mut args = args.peekable();
+                    let code = match args.next_if(|a| a.as_rule() == string_literal) {
+                        Some(s) => string_value(s.as_str()),
+                        None => String::new(),
+                    };
+                    Intrinsic(name, code, args.map(Self::parse).collect::<Result<_>>()?)
+                };
+
+            compound_literal
+                [.. parts,] -> {
+                    let mut ty_parts = parts.next().unwrap().into_inner();
+                    let base = DType::parse(ty_parts.next().unwrap())?;
+                    let decl = ty_parts.next().map(Declarator::parse).transpose()?;
+                    let init = Self::parse(parts.next().unwrap())?;
+
+                    // `(T[]){...}` is as long as its initializer
+                    let InitList(es) = &init else { unreachable!() };
+                    let decl = decl.unwrap_or(Declarator::Abstract).sized(es.len() as Word);
+
+                    let name = format!("<compound@{}>", pair.as_span().start());
+                    CompoundLit(name, decl.set_type(base), init.into())
+                };
+
             postfix_expr // TODO
                 [e] -> e;
                 [p, ..fixtures] -> {
@@ -49,14 +82,14 @@ impl ASTNode for Expr {
                         base = match fixture.as_rule() {
                             index => {
                                 let id = fixture.into_inner().next().unwrap();
-                                Indexed(boxed, Self::parse(id).into())
+                                Indexed(boxed, Self::parse(id)?.into())
                             }
-                            field => Field(boxed, fixture.as_str().into()),
-                            arrow => Arrow(boxed, fixture.as_str().into()),
+                            field => Field(boxed, fixture.into_inner().as_str().into()),
+                            arrow => Arrow(boxed, fixture.into_inner().as_str().into()),
                             inc => Inc(boxed),
                             dec => Dec(boxed),
                             call => {
-                                let args = fixture.into_inner().map(Expr::parse).collect();
+                                let args = fixture.into_inner().map(Expr::parse).collect::<Result<_>>()?;
                                 FnCall(boxed, args)
                             }
                             _ => unreachable!(),
@@ -98,8 +131,8 @@ impl ASTNode for Expr {
                     let mut args = vec![];
                     while let Some((op, arg)) = xs.next().zip(xs.next()) {
                         args.push((
-                            ASTNode::parse(op),
-                            ASTNode::parse(arg)
+                            ASTNode::parse(op)?,
+                            ASTNode::parse(arg)?
                         ));
                     }
                     BinOpExpr(acc, args)
@@ -115,89 +148,160 @@ impl ASTNode for Expr {
 
             expr
                 [.. es,] -> {
-                    Seq(es.map(Self::parse).collect())
+                    Seq(es.map(Self::parse).collect::<Result<_>>()?)
                 };
 
             initializer
                 [e] -> e;
 
             initializer_list
-                [.. es,] -> InitList(es.map(Self::parse).collect());
+                [.. es,] -> InitList(es.map(Self::parse).collect::<Result<_>>()?);
         }
     }
 
-    fn compile(&self, ctxt: &mut CompileContext) {
+    fn compile(&self, ctxt: &mut CompileContext) -> Result<()> {
         use Expr::*;
         use StackInst::*;
+
+        // Fold constant expressions
+        if let BinOpExpr(_, _) | Unary(_, _) | Cast(_, _) | Cond(_, _, _) | Seq(_) | Var(_) = self
+            && let Some((v, _)) = self.const_eval(Some(ctxt))
+        {
+            ctxt.push_const(v, &self.ty(ctxt));
+            return Ok(());
+        }
+
+        // `const` objects & arrays may only be initialized
+        if let Assign(e, _, _) | Inc(e) | Dec(e) | Unary(MonOp::Inc | MonOp::Dec, e) = self {
+            let ty = e.ty(ctxt);
+            if let DType::Array(_, _) = ty.unqualified() {
+                return Err(ctxt.diagnostic("array is not assignable"));
+            }
+            if ty.is_const() {
+                return Err(ctxt.diagnostic("cannot assign to a `const` object"));
+            }
+        }
+
         match self {
-            Const(v) => ctxt.emit(Push(*v as Word)),
+            Const(v) => ctxt.push_const(*v as i128, &self.ty(ctxt)),
             Var(v) => {
-                if let DType::Array(_, _) = ctxt.vty(v) {
-                    ctxt.push_addr(v);
-                    return;
+                if let Some(DType::Array(_, _)) = ctxt.vty(v) {
+                    ctxt.push_addr(v)?;
+                    return Ok(());
                 }
-                ctxt.push_var(v);
+                ctxt.push_var(v)?;
             }
             Unary(MonOp::LogicalNot, e) => {
-                ctxt.compile(e);
-                ctxt.emit(LNot);
+                ctxt.compile(e)?;
+                ctxt.unary(LNot, &e.ty(ctxt));
             }
-            Unary(MonOp::BinaryNot, e) => {
-                ctxt.compile(e);
-                ctxt.emit(Not);
+            Unary(op @ (MonOp::BinaryNot | MonOp::Negate), e) => {
+                let ty = self.ty(ctxt);
+                ctxt.compile(e)?;
+                ctxt.convert(&e.ty(ctxt), &ty);
+                let op = if matches!(op, MonOp::Negate) {
+                    Negate
+                } else {
+                    Not
+                };
+                ctxt.unary(op, &ty);
             }
-            Unary(MonOp::Negate, e) => {
-                ctxt.compile(e);
-                ctxt.emit(Negate);
+            // Values are already kept in their promoted form
+            Unary(MonOp::Plus, e) => ctxt.compile(e)?,
+            // Only the operand's type matters, so it is never evaluated
+            Unary(MonOp::SizeOf, e) => {
+                let ty = e.ty(ctxt);
+                if let DType::Function(_, _) = ty {
+                    return Err(ctxt.diagnostic("`sizeof` applied to a function"));
+                }
+                ctxt.emit(Push(ty.size()));
+            }
+            // Wider integers, & pointers, which step by whole elements, are
+            // added to like any other
+            Unary(op @ (MonOp::Inc | MonOp::Dec), e) if steps(&e.ty(ctxt)) => {
+                let op = match op {
+                    MonOp::Inc => AssignOp::PlusAssign,
+                    _ => AssignOp::SubAssign,
+                };
+                ctxt.compile(&Assign(e.clone(), op, Const(1).into()))?;
+            }
+            Inc(e) | Dec(e) if steps(&e.ty(ctxt)) => {
+                // The old value is the new one, taken back
+                let (op, back) = match self {
+                    Inc(_) => (AssignOp::PlusAssign, BinOp::Sub),
+                    _ => (AssignOp::SubAssign, BinOp::Add),
+                };
+                let new = Assign(e.clone(), op, Const(1).into());
+                ctxt.compile(&BinOpExpr(new.into(), vec![(back, Const(1))]))?;
             }
             Unary(MonOp::Inc, e) => {
-                let Expr::Var(v) = &**e else { todo!() };
-                ctxt.push_var(v);
-                ctxt.emit_stream(&[Push(1), Add, Copy]);
-                ctxt.store(v);
+                let Expr::Var(v) = &**e else {
+                    return Err(ctxt.diagnostic("`++` & `--` only work on variables"));
+                };
+                ctxt.push_var(v)?;
+                ctxt.emit_stream(&[Push(1), Add]);
+                ctxt.convert(&DType::S16, &e.ty(ctxt));
+                ctxt.emit(Copy);
+                ctxt.store(v)?;
             }
             Unary(MonOp::Dec, e) => {
-                let Expr::Var(v) = &**e else { todo!() };
-                ctxt.push_var(v);
-                ctxt.emit_stream(&[Push(1), Sub, Copy]);
-                ctxt.store(v);
+                let Expr::Var(v) = &**e else {
+                    return Err(ctxt.diagnostic("`++` & `--` only work on variables"));
+                };
+                ctxt.push_var(v)?;
+                ctxt.emit_stream(&[Push(1), Sub]);
+                ctxt.convert(&DType::S16, &e.ty(ctxt));
+                ctxt.emit(Copy);
+                ctxt.store(v)?;
             }
             Unary(MonOp::AddrOf, e) => {
-                e.compile_addr(ctxt);
+                if e.bit_field(ctxt).is_some() {
+                    return Err(ctxt.diagnostic("cannot take the address of a bit-field"));
+                }
+                e.compile_addr(ctxt)?;
             }
-            Unary(MonOp::Deref, e) => {
-                ctxt.compile(e);
-                let height = ctxt.stack_height.unwrap();
-                ctxt.emit_stream(&[
-                    LclRead(height - 1),
-                    Push(height as Word),
-                    Add,
-                    Swap,
-                    Sub,
-                    StkRead,
-                ]);
+            Unary(MonOp::Deref, _) | Indexed(_, _) | Arrow(_, _) => self.compile_read(ctxt)?,
+            Field(e, _) if e.is_lvalue() => self.compile_read(ctxt)?,
+            Field(e, _) => {
+                // Pick the member out of a temporary
+                let size = e.ty(ctxt).size() as usize;
+                let (offset, ty) = self.member(ctxt);
+                let words = ty.size() as usize;
+                ctxt.compile(e)?;
+                for _ in 0..words {
+                    ctxt.emit(LclRead(size - 1 - offset as usize));
+                }
+                ctxt.squash(size, words);
+                if let Some((shift, width)) = self.bit_field(ctxt) {
+                    extract_bits(&ty, shift, width, ctxt);
+                }
             }
             Cond(c, t, f) => {
                 let height = ctxt.stack_height.unwrap();
                 let t_lbl = ctxt.label();
                 let f_lbl = ctxt.label();
                 let leave = ctxt.label();
-                ctxt.compile(c);
+                let ty = self.ty(ctxt);
+                ctxt.compile_cond(c)?;
                 ctxt.emit_stream(&[Branch(t_lbl, f_lbl), Label(t_lbl)]);
                 ctxt.stack_height = Some(height);
-                ctxt.compile(t);
+                ctxt.compile(t)?;
+                ctxt.convert(&t.ty(ctxt), &ty);
+                let out = ctxt.stack_height;
                 ctxt.emit_stream(&[Push(leave), Goto, Label(f_lbl)]);
                 ctxt.stack_height = Some(height);
-                ctxt.compile(f);
+                ctxt.compile(f)?;
+                ctxt.convert(&f.ty(ctxt), &ty);
                 ctxt.emit_stream(&[Push(leave), Goto, Label(leave)]);
-                ctxt.stack_height = Some(height + 1);
+                ctxt.stack_height = out;
             }
             TypeSize(ty) => ctxt.emit(Push(ty.size())),
             BinOpExpr(head, args) => {
-                ctxt.compile(head);
+                ctxt.compile(head)?;
 
                 // Short-circuiting And
                 if let Some((BinOp::LAnd, _)) = args.first() {
+                    ctxt.truth(&head.ty(ctxt));
                     let height = ctxt.stack_height;
                     let last = ctxt.label();
                     let fail = ctxt.label();
@@ -206,7 +310,7 @@ impl ASTNode for Expr {
                         ctxt.stack_height = height;
                         let cont = ctxt.label();
                         ctxt.emit_stream(&[Branch(cont, fail), Label(cont)]);
-                        ctxt.compile(arg);
+                        ctxt.compile_cond(arg)?;
                     }
 
                     ctxt.emit_stream(&[
@@ -220,11 +324,12 @@ impl ASTNode for Expr {
                     ]);
                     ctxt.stack_height = height;
 
-                    return;
+                    return Ok(());
                 }
 
                 // Short-circuiting Or
                 if let Some((BinOp::LOr, _)) = args.first() {
+                    ctxt.truth(&head.ty(ctxt));
                     let height = ctxt.stack_height;
                     let succ = ctxt.label();
                     let last = ctxt.label();
@@ -233,7 +338,7 @@ impl ASTNode for Expr {
                         let cont = ctxt.label();
                         ctxt.stack_height = height;
                         ctxt.emit_stream(&[Branch(succ, cont), Label(cont)]);
-                        ctxt.compile(arg);
+                        ctxt.compile_cond(arg)?;
                     }
 
                     ctxt.emit_stream(&[
@@ -247,10 +352,41 @@ impl ASTNode for Expr {
                     ]);
                     ctxt.stack_height = height;
 
-                    return;
+                    return Ok(());
                 }
 
+                let mut ty = head.ty(ctxt);
+                let mut lhs = Some(&**head); // Unless it is a previous result
                 for (op, arg) in args {
+                    let arg_ty = arg.ty(ctxt);
+                    let elem = |t: &DType| t.decayed().deref().map(|t| t.size()).unwrap_or(1);
+
+                    // Pointer arithmetic counts in elements
+                    let (scale_lhs, scale_rhs, unscale) = match op {
+                        BinOp::Add | BinOp::Sub if ty.is_pointer() && arg_ty.is_pointer() => {
+                            (1, 1, elem(&ty))
+                        }
+                        BinOp::Add | BinOp::Sub if ty.is_pointer() => (1, elem(&ty), 1),
+                        BinOp::Add if arg_ty.is_pointer() => (elem(&arg_ty), 1, 1),
+                        _ => (1, 1, 1),
+                    };
+                    check_unsigned_compare(op, (lhs.take(), &ty), (arg, &arg_ty), ctxt);
+
+                    // Both operands are converted to the type the operator works
+                    // in, except for pointers, which are always one word
+                    let pointers = ty.is_pointer() || arg_ty.is_pointer();
+                    let operand_ty = match pointers {
+                        true => DType::U16,
+                        false => operand_ty(op, &ty, &arg_ty),
+                    };
+                    let to = |t: &DType| match t.is_arithmetic() {
+                        true => operand_ty.clone(),
+                        false => t.clone(),
+                    };
+                    let (lhs_ty, rhs_ty) = (to(&ty), to(&arg_ty));
+                    ctxt.convert(&ty, &lhs_ty);
+                    ty = binop_ty(op, &ty, &arg_ty);
+
                     let op = match op {
                         BinOp::Add => Add,
                         BinOp::Sub => Sub,
@@ -271,46 +407,85 @@ impl ASTNode for Expr {
                         _ => unreachable!(),
                     };
 
-                    ctxt.compile(arg);
-                    ctxt.emit(op);
+                    ctxt.scale(scale_lhs);
+                    ctxt.compile(arg)?;
+                    ctxt.convert(&arg_ty, &rhs_ty);
+                    ctxt.scale(scale_rhs);
+                    ctxt.arith(op, &operand_ty);
+                    if unscale != 1 {
+                        ctxt.emit(Push(unscale));
+                        ctxt.arith(Div, &DType::S16);
+                    }
                 }
             }
             FnCall(func, args) => {
-                ctxt.call_fn(func, args);
+                ctxt.call_fn(func, args)?;
             }
             Seq(seqs) => {
+                let height = ctxt.stack_height.unwrap();
                 let mut seqs = seqs.iter();
-                ctxt.compile(seqs.next().unwrap());
+                ctxt.compile(seqs.next().unwrap())?;
 
                 for seq in seqs {
-                    ctxt.emit(Dealloc(1));
-                    ctxt.compile(seq);
+                    ctxt.emit(Dealloc(ctxt.stack_height.unwrap() - height));
+                    ctxt.compile(seq)?;
                 }
             }
 
-            Assign(var, AssignOp::Assign, val) => {
-                ctxt.compile(val);
+            Assign(var, AssignOp::Assign, val)
+                if let Some((shift, width)) = var.bit_field(ctxt) =>
+            {
+                let ty = var.ty(ctxt);
+                let mask = bit_mask(width);
+                ctxt.check_implicit(&ty, val);
+                ctxt.compile(val)?;
+                ctxt.convert(&val.ty(ctxt), &ty);
+                ctxt.emit_stream(&[Push(mask), And, Copy, Push(shift), LShift]);
+
+                // Replace just the field's bits in its cell, by taking out
+                // the old ones & or-ing in the new
+                var.compile_addr(ctxt)?;
                 ctxt.emit(Copy);
+                ctxt.load(1);
+                ctxt.emit_stream(&[Copy, Push(shift), RShift, Push(mask), And]);
+                ctxt.emit_stream(&[Push(shift), LShift, Sub, LclRead(2), Or, Swap]);
+                ctxt.store_addr(1);
+                ctxt.emit(Dealloc(1));
+                sign_extend(&ty, width, ctxt);
+            }
+            Assign(var, op, val)
+                if var.bit_field(ctxt).is_some()
+                    || op.binop().is_some_and(|b| {
+                        words(&operand_ty(&b, &var.ty(ctxt), &val.ty(ctxt))) > 1
+                    })
+                    || (op.binop().is_some() && var.ty(ctxt).is_pointer()) =>
+            {
+                // Bit-fields & wider integers can't be updated in place, &
+                // pointers count in elements, so `a op= b` becomes `a = a op b`
+                let value = BinOpExpr(var.clone(), vec![(op.binop().unwrap(), (**val).clone())]);
+                ctxt.compile(&Assign(var.clone(), AssignOp::Assign, value.into()))?;
+            }
+            Assign(var, AssignOp::Assign, val) => {
+                let ty = var.ty(ctxt);
+                ctxt.check_implicit(&ty, val);
+                let words = words(&ty);
+                ctxt.compile(val)?;
+                ctxt.convert(&val.ty(ctxt), &ty);
+                ctxt.dup(words);
                 if let Expr::Var(v) = &**var {
-                    ctxt.store(v);
+                    ctxt.store(v)?;
                 } else {
-                    var.compile_addr(ctxt);
-                    let height = ctxt.stack_height.unwrap();
-                    ctxt.emit_stream(&[
-                        LclRead(height - 1),
-                        Push(height as Word - 2),
-                        Add,
-                        Swap,
-                        Sub,
-                        StkStr,
-                    ]);
+                    var.compile_addr(ctxt)?;
+                    ctxt.store_addr(words);
                 }
             }
 
             Assign(var, op, val) => {
+                let binop = op.binop().unwrap();
                 let op = match op {
                     AssignOp::MulAssign => Mul,
                     AssignOp::DivAssign => Div,
+                    AssignOp::ModAssign => Mod,
                     AssignOp::PlusAssign => Add,
                     AssignOp::SubAssign => Sub,
                     AssignOp::LShiftAssign => LShift,
@@ -321,7 +496,7 @@ impl ASTNode for Expr {
                     _ => unreachable!(),
                 };
 
-                var.compile_addr(ctxt);
+                var.compile_addr(ctxt)?;
                 ctxt.emit(Copy);
                 let height = ctxt.stack_height.unwrap();
                 ctxt.emit_stream(&[
@@ -333,8 +508,11 @@ impl ASTNode for Expr {
                     StkRead,
                 ]);
 
-                ctxt.compile(val);
-                ctxt.emit_stream(&[op, Swap, LclRead(1), Swap]);
+                let ty = var.ty(ctxt);
+                ctxt.compile(val)?;
+                ctxt.arith(op, &operand_ty(&binop, &ty, &val.ty(ctxt)));
+                ctxt.convert(&DType::S16, &ty);
+                ctxt.emit_stream(&[Swap, LclRead(1), Swap]);
 
                 let height = ctxt.stack_height.unwrap();
                 ctxt.emit_stream(&[
@@ -348,97 +526,599 @@ impl ASTNode for Expr {
             }
 
             Inc(e) => {
-                let Expr::Var(v) = &**e else { todo!() };
-                ctxt.push_var(v);
+                let Expr::Var(v) = &**e else {
+                    return Err(ctxt.diagnostic("`++` & `--` only work on variables"));
+                };
+                ctxt.push_var(v)?;
                 ctxt.emit_stream(&[Copy, Push(1), Add]);
-                ctxt.store(v);
+                ctxt.convert(&DType::S16, &e.ty(ctxt));
+                ctxt.store(v)?;
             }
             Dec(e) => {
-                let Expr::Var(v) = &**e else { todo!() };
-                ctxt.push_var(v);
+                let Expr::Var(v) = &**e else {
+                    return Err(ctxt.diagnostic("`++` & `--` only work on variables"));
+                };
+                ctxt.push_var(v)?;
                 ctxt.emit_stream(&[Copy, Push(1), Sub]);
-                ctxt.store(v);
+                ctxt.convert(&DType::S16, &e.ty(ctxt));
+                ctxt.store(v)?;
             }
 
-            Indexed(_, _) => {
-                self.compile_addr(ctxt);
-                let height = ctxt.stack_height.unwrap();
-                ctxt.emit_stream(&[
-                    LclRead(height - 1),
-                    Push(height as Word - 1),
-                    Add,
-                    Swap,
-                    Sub,
-                    StkRead,
-                ]);
+            Intrinsic(name, code, args) => {
+                Self::compile_intrinsic(name, code, args, ctxt)?;
+            }
+            // An unnamed variable, initialized each time it is evaluated
+            CompoundLit(v, ty, init) => {
+                init.compile_init(ty, ctxt)?;
+                ctxt.store(v)?;
+                ctxt.compile(&Var(v.clone()))?;
+            }
+            Cast(ty, e) => {
+                let from = e.ty(ctxt);
+                let scalar = |t: &DType| t.is_arithmetic() || t.is_pointer();
+                if words(&from) != words(ty) && !(scalar(&from) && scalar(ty)) {
+                    let msg = format!("unsupported conversion from `{}` to `{}`", from, ty);
+                    return Err(ctxt.diagnostic(msg));
+                }
+                ctxt.compile(e)?;
+                ctxt.convert(&from, ty);
             }
 
-            e => todo!("Unsupported expr {:?}", e),
+            InitList(_) => {
+                return Err(ctxt.diagnostic("braced initializer is not allowed here"));
+            }
         };
+
+        Ok(())
     }
 }
 
 impl Expr {
     pub fn const_arithmetic_expr(&self) -> Option<u64> {
+        self.const_eval(None).and_then(|(v, _)| v.try_into().ok())
+    }
+
+    // Value & type of an integer constant expression. Without a context,
+    // `sizeof` only knows the variables the parser has seen declared.
+    pub fn const_eval(&self, ctxt: Option<&CompileContext>) -> Option<(i128, DType)> {
+        use DType::*;
         use Expr::*;
+        let truth = |b: bool| Some((b as i128, S16));
         match self {
-            Const(v) => Some(*v as u64),
-            BinOpExpr(expr, items) => {
-                let mut e_val = expr.const_arithmetic_expr()?;
-                for (op, e) in items {
-                    let operand = e.const_arithmetic_expr()?;
+            Const(v) => {
+                let v = *v as i128;
+                let ty = [S16, U16, S32, U32, S64, U64]
+                    .into_iter()
+                    .find(|t| t.wrap(v) == v)?;
+                Some((v, ty))
+            }
+            Var(v) => ctxt?.constant(v),
+            // Parentheses
+            Seq(es) if es.len() == 1 => es[0].const_eval(ctxt),
+            TypeSize(ty) => Some((ty.size() as i128, U16)),
+            Unary(MonOp::SizeOf, e) => {
+                match ctxt.map_or_else(|| e.parsed_ty(), |c| Some(e.ty(c)))? {
+                    Function(_, _) => None,
+                    ty => Some((ty.size() as i128, U16)),
+                }
+            }
+            Unary(op, e) => {
+                let (v, ty) = e.const_eval(ctxt)?;
+                let ty = ty.common(&ty);
+                match op {
+                    MonOp::Negate => Some((ty.wrap(-v), ty)),
+                    MonOp::Plus => Some((v, ty)),
+                    MonOp::BinaryNot => Some((ty.wrap(!v), ty)),
+                    MonOp::LogicalNot => truth(v == 0),
+                    _ => None,
+                }
+            }
+            Cast(ty, e) if ty.is_arithmetic() && !matches!(ty, Float | Double) => {
+                let (v, _) = e.const_eval(ctxt)?;
+                Some((ty.wrap(v), ty.clone()))
+            }
+            Cond(c, t, f) => {
+                let (c, _) = c.const_eval(ctxt)?;
+                let (t, t_ty) = t.const_eval(ctxt)?;
+                let (f, f_ty) = f.const_eval(ctxt)?;
+                let ty = t_ty.common(&f_ty);
+                Some((ty.wrap(if c != 0 { t } else { f }), ty))
+            }
+            BinOpExpr(head, args) => {
+                let (mut v, mut ty) = head.const_eval(ctxt)?;
+                for (op, arg) in args {
                     use BinOp::*;
-                    e_val = match op {
-                        Add => e_val + operand,
-                        Sub => e_val - operand,
-                        Mul => e_val * operand,
-                        Div => e_val / operand,
-                        Mod => e_val % operand,
-                        LShift => e_val << operand,
-                        RShift => e_val >> operand,
-                        Xor => e_val ^ operand,
-                        And => e_val & operand,
-                        Or => e_val | operand,
-                        _ => todo!(),
+
+                    // Only evaluate what the program would
+                    match op {
+                        LAnd if v == 0 => {
+                            ty = S16;
+                            continue;
+                        }
+                        LOr if v != 0 => {
+                            (v, ty) = (1, S16);
+                            continue;
+                        }
+                        _ => (),
+                    }
+
+                    let (w, w_ty) = arg.const_eval(ctxt)?;
+
+                    // Shifts don't convert their operands to a common type
+                    if let LShift | RShift = op {
+                        ty = ty.common(&ty);
+                        v = match op {
+                            LShift => ty.wrap(v << (w & 127)),
+                            _ => v >> (w & 127),
+                        };
+                        continue;
+                    }
+
+                    let common = ty.common(&w_ty);
+                    let (a, b) = (common.wrap(v), common.wrap(w));
+                    (v, ty) = match op {
+                        Add => (common.wrap(a + b), common),
+                        Sub => (common.wrap(a - b), common),
+                        Mul => (common.wrap(a * b), common),
+                        // C division truncates towards zero, like Rust's
+                        Div => (common.wrap(a.checked_div(b)?), common),
+                        Mod => (common.wrap(a.checked_rem(b)?), common),
+                        And => (a & b, common),
+                        Or => (a | b, common),
+                        Xor => (a ^ b, common),
+                        Eq => (truth(a == b)?.0, S16),
+                        Neq => (truth(a != b)?.0, S16),
+                        Lt => (truth(a < b)?.0, S16),
+                        LtEq => (truth(a <= b)?.0, S16),
+                        Gr => (truth(a > b)?.0, S16),
+                        GrEq => (truth(a >= b)?.0, S16),
+                        LAnd | LOr => (truth(b != 0)?.0, S16),
+                        LShift | RShift => unreachable!(),
                     };
                 }
 
-                Some(e_val)
+                Some((v, ty))
+            }
+            _ => None,
+        }
+    }
+
+    // Compiles an initializer for an object of the given type
+    pub fn compile_init(&self, ty: &DType, ctxt: &mut CompileContext) -> Result<()> {
+        use DType::*;
+        use StackInst::*;
+        let members: Vec<(DType, Slot)> = match ty.unqualified() {
+            Array(n, t) => (0..*n)
+                .map(|i| ((**t).clone(), (i * t.size(), None)))
+                .collect(),
+            Struct(_) => match ty.aggregate() {
+                Some(a) => {
+                    let (slots, _) = a.layout();
+                    let named = a
+                        .members
+                        .into_iter()
+                        .zip(slots)
+                        .filter(|((m, _, _), _)| !m.is_empty());
+                    // Only the first member of a union is initialized
+                    let n = if a.union { 1 } else { usize::MAX };
+                    named.take(n).map(|((_, t, _), slot)| (t, slot)).collect()
+                }
+                None => vec![],
+            },
+            _ => match self {
+                Expr::InitList(es) if !es.is_empty() => return es[0].compile_init(ty, ctxt),
+                _ => {
+                    ctxt.compile(self)?;
+                    ctxt.convert(&self.ty(ctxt), ty);
+                    return Ok(());
+                }
+            },
+        };
+
+        let Expr::InitList(es) = self else {
+            return ctxt.compile(self);
+        };
+
+        if es.len() > members.len() {
+            ctxt.error(format!("too many initializers for `{}`", ty));
+        }
+
+        let mut words = 0;
+        for (i, (member, (offset, bits))) in members.iter().enumerate() {
+            let Some((shift, width)) = bits else {
+                match es.get(i) {
+                    Some(e) => e.compile_init(member, ctxt)?,
+                    None => ctxt.zeros(member.size() as usize),
+                }
+                words = offset + member.size();
+                continue;
+            };
+
+            // Bit-fields are or-ed into their cell
+            if *offset >= words {
+                ctxt.zeros((offset + 1 - words) as usize);
+                words = offset + 1;
             }
-            Unary(op, expr) => {
-                let e_val = expr.const_arithmetic_expr()?;
-                use MonOp::*;
+            if let Some(e) = es.get(i) {
+                e.compile_init(member, ctxt)?;
+                ctxt.emit_stream(&[Push(bit_mask(*width)), And, Push(*shift), LShift, Or]);
+            }
+        }
+
+        // Rest of a union
+        ctxt.zeros((ty.size() - words) as usize);
+        Ok(())
+    }
+
+    fn is_lvalue(&self) -> bool {
+        use Expr::*;
+        match self {
+            Var(_)
+            | Unary(MonOp::Deref, _)
+            | Indexed(_, _)
+            | Arrow(_, _)
+            | CompoundLit(_, _, _) => true,
+            Field(e, _) => e.is_lvalue(),
+            _ => false,
+        }
+    }
+
+    // Reads an object through its address. Arrays are left as their address.
+    fn compile_read(&self, ctxt: &mut CompileContext) -> Result<()> {
+        let ty = self.ty(ctxt);
+        self.compile_addr(ctxt)?;
+        if !matches!(ty, DType::Array(_, _)) {
+            ctxt.load(words(&ty));
+        }
+        if let Some((shift, width)) = self.bit_field(ctxt) {
+            extract_bits(&ty, shift, width, ctxt);
+        }
+        Ok(())
+    }
+
+    // Shift & width of the member accessed by a `Field` or `Arrow` expression,
+    // if it is a bit-field
+    fn bit_field(&self, ctxt: &CompileContext) -> Option<(Word, Word)> {
+        match self {
+            Expr::Field(e, m) => e.ty(ctxt).bit_field(m),
+            Expr::Arrow(e, m) => e.ty(ctxt).decayed().deref()?.bit_field(m),
+            _ => None,
+        }
+    }
+
+    // Offset & type of the member accessed by a `Field` or `Arrow` expression
+    fn member(&self, ctxt: &mut CompileContext) -> (Word, DType) {
+        let (agg, m) = match self {
+            Expr::Field(e, m) => (e.ty(ctxt), m),
+            Expr::Arrow(e, m) => (e.ty(ctxt).decayed().deref().unwrap_or(DType::Void), m),
+            _ => unreachable!(),
+        };
+
+        agg.member(m).unwrap_or_else(|| {
+            ctxt.error(format!("no member named `{}` in `{}`", m, agg));
+            (0, DType::S16)
+        })
+    }
+
+    // Static type of the expression
+    pub fn ty(&self, ctxt: &CompileContext) -> DType {
+        use DType::*;
+        use Expr::*;
+        match self {
+            Const(_) => self.const_eval(None).map_or(S16, |(_, t)| t),
+            Var(v) => ctxt.vty(v).cloned().unwrap_or(S16),
+            BinOpExpr(head, args) => {
+                let mut ty = head.ty(ctxt);
+                for (op, arg) in args {
+                    ty = binop_ty(op, &ty, &arg.ty(ctxt));
+                }
+                ty
+            }
+            Unary(op, e) => {
+                let ty = e.ty(ctxt);
                 match op {
-                    BinaryNot => Some(!e_val),
-                    _ => todo!(),
+                    MonOp::Deref => ty.decayed().deref().unwrap_or(S16),
+                    MonOp::AddrOf => ty.pointer(),
+                    MonOp::LogicalNot => S16,
+                    MonOp::SizeOf => U16,
+                    MonOp::Negate | MonOp::Plus | MonOp::BinaryNot => ty.common(&ty),
+                    MonOp::Inc | MonOp::Dec => ty,
                 }
             }
-            TypeSize(dtype) => Some(dtype.size() as u64),
+            TypeSize(_) => U16,
+            Cast(t, _) | CompoundLit(_, t, _) => t.clone(),
+            Cond(_, t, f) => {
+                let (t, f) = (t.ty(ctxt), f.ty(ctxt));
+                match t.is_arithmetic() && f.is_arithmetic() {
+                    true => t.common(&f),
+                    false => t,
+                }
+            }
+            Assign(v, _, _) | Inc(v) | Dec(v) => v.ty(ctxt),
+            Seq(es) => es.last().map(|e| e.ty(ctxt)).unwrap_or(Void),
+            Indexed(arr, _) => arr.ty(ctxt).decayed().deref().unwrap_or(S16),
+            FnCall(func, _) => match func.ty(ctxt).decayed().deref() {
+                Some(Function(_, ret)) => *ret,
+                _ => S16,
+            },
+            Field(e, m) => e.ty(ctxt).member(m).map(|(_, t)| t).unwrap_or(S16),
+            Arrow(e, m) => {
+                let agg = e.ty(ctxt).decayed().deref().unwrap_or(Void);
+                agg.member(m).map(|(_, t)| t).unwrap_or(S16)
+            }
+            InitList(_) => Void,
+            Intrinsic(name, code, args) => Self::intrinsic_ty(name, code, args, ctxt),
+        }
+    }
+
+    // The type of an object, from the declarations seen so far by the parser
+    fn parsed_ty(&self) -> Option<DType> {
+        use Expr::*;
+        match self {
+            Var(v) => var_type(v),
+            Seq(es) if es.len() == 1 => es[0].parsed_ty(),
+            Indexed(e, _) | Unary(MonOp::Deref, e) => e.parsed_ty()?.decayed().deref(),
             _ => None,
         }
     }
 
-    pub fn compile_addr(&self, ctxt: &mut CompileContext) {
+    // Visits every identifier naming an object or function
+    pub fn visit_idents(&mut self, f: &mut impl FnMut(&mut Ident)) {
+        use Expr::*;
+        match self {
+            Const(_) | TypeSize(_) => (),
+            Var(v) => f(v),
+            Unary(_, e) | Cast(_, e) | Field(e, _) | Arrow(e, _) | Inc(e) | Dec(e) => {
+                e.visit_idents(f)
+            }
+            BinOpExpr(head, args) => {
+                head.visit_idents(f);
+                for (_, arg) in args {
+                    arg.visit_idents(f);
+                }
+            }
+            Cond(c, t, e) => {
+                c.visit_idents(f);
+                t.visit_idents(f);
+                e.visit_idents(f);
+            }
+            Assign(a, _, b) | Indexed(a, b) => {
+                a.visit_idents(f);
+                b.visit_idents(f);
+            }
+            Seq(es) | InitList(es) => {
+                for e in es {
+                    e.visit_idents(f);
+                }
+            }
+            FnCall(func, args) => {
+                func.visit_idents(f);
+                for arg in args {
+                    arg.visit_idents(f);
+                }
+            }
+            Intrinsic(_, _, args) => {
+                for arg in args {
+                    arg.visit_idents(f);
+                }
+            }
+            CompoundLit(v, _, init) => {
+                f(v);
+                init.visit_idents(f);
+            }
+        }
+    }
+
+    // Objects made by the compound literals in this expression, which need
+    // storage just like variables
+    pub fn compound_literals(&self) -> Vec<(DType, Option<Ident>)> {
+        let mut found = vec![];
+        self.visit(&mut |e| {
+            if let Expr::CompoundLit(v, ty, _) = e {
+                found.push((ty.clone(), Some(v.clone())));
+            }
+        });
+        found
+    }
+
+    // Whether some constant is divided by 0, keeping the expression from being constant
+    pub fn divides_by_zero(&self) -> bool {
+        let mut found = false;
+        self.visit(&mut |e| {
+            if let Expr::BinOpExpr(_, args) = e {
+                found |= args.iter().any(|(op, arg)| {
+                    matches!(op, BinOp::Div | BinOp::Mod)
+                        && arg.const_eval(None).is_some_and(|(v, _)| v == 0)
+                });
+            }
+        });
+        found
+    }
+
+    // Visits this expression, & every one inside it
+    pub fn visit(&self, f: &mut impl FnMut(&Expr)) {
+        f(self);
+        for e in self.subexprs() {
+            e.visit(f);
+        }
+    }
+
+    fn subexprs(&self) -> Vec<&Expr> {
+        use Expr::*;
+        match self {
+            Const(_) | TypeSize(_) | Var(_) => vec![],
+            Unary(_, e) | Cast(_, e) | Field(e, _) | Arrow(e, _) | Inc(e) | Dec(e) => vec![e],
+            CompoundLit(_, _, e) => vec![e],
+            BinOpExpr(head, args) => std::iter::once(&**head)
+                .chain(args.iter().map(|(_, arg)| arg))
+                .collect(),
+            Cond(c, t, e) => vec![c, t, e],
+            Assign(a, _, b) | Indexed(a, b) => vec![a, b],
+            Seq(es) | InitList(es) | Intrinsic(_, _, es) => es.iter().collect(),
+            FnCall(func, args) => std::iter::once(&**func).chain(args).collect(),
+        }
+    }
+
+    pub fn compile_addr(&self, ctxt: &mut CompileContext) -> Result<()> {
         use Expr::*;
         use StackInst::*;
         match self {
-            Var(v) => ctxt.push_addr(v),
-            Unary(MonOp::Deref, e) => ctxt.compile(e),
+            Var(v) => ctxt.push_addr(v)?,
+            Unary(MonOp::Deref, e) => ctxt.compile(e)?,
+            CompoundLit(v, ty, init) => {
+                init.compile_init(ty, ctxt)?;
+                ctxt.store(v)?;
+                ctxt.push_addr(v)?;
+            }
             Seq(es) => {
                 let n = es.len();
                 for e in &es[0..n - 1] {
-                    ctxt.compile(e);
+                    ctxt.compile(e)?;
                     ctxt.emit(Dealloc(1));
                 }
-                es[n - 1].compile_addr(ctxt);
+                es[n - 1].compile_addr(ctxt)?;
             }
             Indexed(arr, id) => {
-                ctxt.compile(arr);
-                ctxt.compile(id);
-                // Will not work for multidimensional arrays
+                let elem = self.ty(ctxt).size();
+                ctxt.compile(arr)?;
+                ctxt.compile(id)?;
+                ctxt.convert(&id.ty(ctxt), &DType::U16);
+                ctxt.scale(elem);
                 ctxt.emit(Add);
             }
-            _ => unreachable!("{:?}", self),
+            Field(e, _) => {
+                let (offset, _) = self.member(ctxt);
+                e.compile_addr(ctxt)?;
+                ctxt.emit_stream(&[Push(offset), Add]);
+            }
+            Arrow(e, _) => {
+                let (offset, _) = self.member(ctxt);
+                ctxt.compile(e)?;
+                ctxt.emit_stream(&[Push(offset), Add]);
+            }
+            _ => return Err(ctxt.diagnostic("expression is not assignable")),
+        }
+
+        Ok(())
+    }
+}
+
+// Warns about comparing an unsigned value against 0, where the result never changes
+fn check_unsigned_compare(
+    op: &BinOp,
+    (lhs, lhs_ty): (Option<&Expr>, &DType),
+    (rhs, rhs_ty): (&Expr, &DType),
+    ctxt: &mut CompileContext,
+) {
+    let zero = |e: &Expr| matches!(e.const_eval(Some(ctxt)), Some((0, _)));
+    let unsigned = |t: &DType| t.is_arithmetic() && !t.is_signed();
+
+    let result = match op {
+        BinOp::GrEq if unsigned(lhs_ty) && zero(rhs) => "true",
+        BinOp::Lt if unsigned(lhs_ty) && zero(rhs) => "false",
+        BinOp::LtEq if lhs.is_some_and(zero) && unsigned(rhs_ty) => "true",
+        BinOp::Gr if lhs.is_some_and(zero) && unsigned(rhs_ty) => "false",
+        _ => return,
+    };
+
+    let msg = format!("comparison of unsigned value with 0 is always {}", result);
+    ctxt.warn(Warning::UnsignedCompare, msg);
+}
+
+// Type of the result of a binary operator
+fn binop_ty(op: &BinOp, lhs: &DType, rhs: &DType) -> DType {
+    use BinOp::*;
+    match op {
+        Eq | Neq | Lt | LtEq | Gr | GrEq | LAnd | LOr => DType::S16,
+        Add | Sub if lhs.is_pointer() && rhs.is_pointer() => DType::S16,
+        Add | Sub if lhs.is_pointer() => lhs.decayed(),
+        Add if rhs.is_pointer() => rhs.decayed(),
+        LShift | RShift => lhs.common(lhs),
+        _ => lhs.common(rhs),
+    }
+}
+
+// Whether `++` & `--` on this type take more than adding one to a word
+fn steps(ty: &DType) -> bool {
+    words(ty) > 1 || ty.is_pointer()
+}
+
+// Type a binary operator works in, after the usual arithmetic conversions.
+// Shifts only promote their left operand.
+fn operand_ty(op: &BinOp, lhs: &DType, rhs: &DType) -> DType {
+    use BinOp::*;
+    match op {
+        LShift | RShift => lhs.common(lhs),
+        _ => lhs.common(rhs),
+    }
+}
+
+// Takes a bit-field out of the cell on top of the stack
+fn extract_bits(ty: &DType, shift: Word, width: Word, ctxt: &mut CompileContext) {
+    use StackInst::*;
+    ctxt.emit_stream(&[Push(shift), RShift, Push(bit_mask(width)), And]);
+    sign_extend(ty, width, ctxt);
+}
+
+// Spreads the top bit of a signed bit-field through the rest of the word
+fn sign_extend(ty: &DType, width: Word, ctxt: &mut CompileContext) {
+    use StackInst::*;
+    if ty.is_signed() && width < Word::BITS as Word {
+        let sign = 1 << (width - 1);
+        ctxt.emit_stream(&[Push(sign), Xor, Push(sign), Sub]);
+    }
+}
+
+// Lowest `width` bits of a word
+fn bit_mask(width: Word) -> Word {
+    ((1u32 << width) - 1) as Word
+}
+
+// Contents of a string literal, with escapes read like a character's
+fn string_value(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s[1..s.len() - 1].chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut c = c.to_string();
+        if c == "\\" {
+            // As much as the escape takes, like the grammar's `escape`
+            let first = chars.next().unwrap();
+            c.push(first);
+            let (digit, max): (fn(&char) -> bool, usize) = match first {
+                'x' => (char::is_ascii_hexdigit, usize::MAX),
+                'u' => (char::is_ascii_hexdigit, 4),
+                '0'..='7' => (|c| c.is_digit(8), 2),
+                _ => (|_| false, 0),
+            };
+            for _ in 0..max {
+                match chars.next_if(digit) {
+                    Some(d) => c.push(d),
+                    None => break,
+                }
+            }
         }
+        out.push(char::from_u32(char_value(&c) as u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+    }
+    out
+}
+
+// Value of the contents of a character constant, e.g. `a` or `\n`
+fn char_value(c: &str) -> usize {
+    let Some(esc) = c.strip_prefix('\\') else {
+        return c.chars().next().unwrap() as usize;
+    };
+
+    match esc {
+        "n" => 10,
+        "t" => 9,
+        "r" => 13,
+        "a" => 7,
+        "b" => 8,
+        "f" => 12,
+        "v" => 11,
+        _ if esc.starts_with(['x', 'u']) => usize::from_str_radix(&esc[1..], 16).unwrap(),
+        _ if esc.starts_with(|c: char| c.is_digit(8)) => usize::from_str_radix(esc, 8).unwrap(),
+        // `\\`, `\'`, `\"`, `\?`
+        _ => esc.chars().next().unwrap() as usize,
     }
 }
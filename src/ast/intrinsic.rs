@@ -0,0 +1,153 @@
+use super::*;
+
+// Built-ins written like calls, whose first argument is a string of code:
+//
+// `__bf(code, pops, pushes, operands...)` splices in raw BF. The head starts on
+// the top of the stack, after the operands have been pushed, & the BF has to
+// pop & push the declared # of words, or the stack machine traps. Its brackets
+// have to match.
+//
+// `__stackir(code, operands...)` splices in stack instructions, such as
+// `Push(3) Add`. Their stack effect comes from the instructions themselves.
+//
+// Either way, the operands & code together must leave one word (an `int`
+// result) or none (`void`).
+//
+// `__debug(label)`, `__dump_locals()` & `__breakpoint()` show the stack while
+// debugging, & compile to nothing in release BF.
+impl Expr {
+    pub(crate) fn compile_intrinsic(
+        name: &str,
+        code: &str,
+        args: &[Expr],
+        ctxt: &mut CompileContext,
+    ) -> Result<()> {
+        use StackInst::*;
+        let height = ctxt.stack_height.unwrap();
+
+        let (insts, operands) =
+            intrinsic_code(name, code, args, ctxt).map_err(|e| ctxt.diagnostic(e))?;
+
+        for e in operands {
+            if words(&e.ty(ctxt)) != 1 {
+                return Err(ctxt.diagnostic(format!("operands of `{}` must be one word", name)));
+            }
+            ctxt.compile(e)?;
+        }
+        ctxt.emit_stream(&insts);
+
+        match ctxt.stack_height.unwrap() as isize - height as isize {
+            0 => ctxt.emit(Push(0)),
+            1 => (),
+            n => {
+                let msg = format!("`{}` must leave 0 or 1 words, but leaves {}", name, n);
+                return Err(ctxt.diagnostic(msg));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Whether an intrinsic has a value, rather than being `void`
+    pub(crate) fn intrinsic_ty(
+        name: &str,
+        code: &str,
+        args: &[Expr],
+        ctxt: &CompileContext,
+    ) -> DType {
+        if matches!(name, "__debug" | "__dump_locals" | "__breakpoint") {
+            return DType::Void;
+        }
+
+        let Ok((insts, operands)) = intrinsic_code(name, code, args, ctxt) else {
+            return DType::S16;
+        };
+
+        let mut height = operands.len() as isize;
+        for inst in &insts {
+            let (i, o) = inst.signature();
+            height += o.unwrap_or(0) as isize - i as isize;
+        }
+
+        if height == 0 { DType::Void } else { DType::S16 }
+    }
+}
+
+// The instructions an intrinsic expands to, & the operands pushed before them
+fn intrinsic_code<'a>(
+    name: &str,
+    code: &str,
+    args: &'a [Expr],
+    ctxt: &CompileContext,
+) -> Result<(Vec<StackInst>, &'a [Expr]), String> {
+    use StackInst::*;
+    match name {
+        "__bf" => {
+            let (pops, pushes, operands) = match args {
+                [] => (0, 0, args),
+                [pops, pushes, operands @ ..] => {
+                    let count = |e: &Expr| {
+                        e.const_arithmetic_expr()
+                            .ok_or("the stack effect of `__bf` must be constant")
+                    };
+                    (count(pops)? as usize, count(pushes)? as usize, operands)
+                }
+                _ => {
+                    return Err(
+                        "`__bf` takes its code, then the # of words it pops & pushes".into(),
+                    );
+                }
+            };
+
+            if pops > operands.len() {
+                return Err(format!(
+                    "`__bf` pops {} words, but only has {} operands",
+                    pops,
+                    operands.len()
+                ));
+            }
+
+            // Every `[` needs a `]` after it, in the same code
+            let mut depth = 0;
+            for c in code.chars() {
+                match c {
+                    '[' => depth += 1,
+                    ']' if depth == 0 => return Err("unmatched `]` in `__bf` code".into()),
+                    ']' => depth -= 1,
+                    _ => (),
+                }
+            }
+            if depth > 0 {
+                return Err("unmatched `[` in `__bf` code".into());
+            }
+
+            Ok((vec![Bf(code.to_string(), pops, pushes)], operands))
+        }
+        "__stackir" => {
+            let insts = StackInst::parse_stream(code)?;
+
+            let mut height = args.len();
+            for inst in &insts {
+                let (i, o) = inst.signature();
+                if matches!(inst, Branch(_, _) | Goto) || o.is_none() {
+                    return Err(format!("`{:?}` is not allowed in `__stackir`", inst));
+                }
+                if i > height {
+                    return Err(format!(
+                        "`{:?}` would pop past the operands of `__stackir`",
+                        inst
+                    ));
+                }
+                height = height - i + o.unwrap();
+            }
+
+            Ok((insts, args))
+        }
+        "__debug" if args.is_empty() => Ok((vec![Debug(code.to_string())], args)),
+        "__debug" => Err("`__debug` only takes a label".into()),
+        _ if !code.is_empty() || !args.is_empty() => Err(format!("`{}` takes no arguments", name)),
+        "__dump_locals" => Ok((vec![Debug(ctxt.dump_locals())], args)),
+        "__breakpoint" => Ok((vec![Debug(BREAKPOINT.to_string())], args)),
+        _ => Err(format!("unknown intrinsic `{}`", name)),
+    }
+}
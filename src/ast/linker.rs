@@ -0,0 +1,214 @@
+use super::*;
+
+use std::collections::*;
+
+#[derive(Debug)]
+pub enum LinkError {
+    UnknownHeader(String),
+    Duplicate(Ident),
+    Missing(Ident),
+    Conflict(Ident),
+}
+
+impl std::fmt::Display for LinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use LinkError::*;
+        match self {
+            UnknownHeader(h) => write!(f, "unknown header `<{}>`", h),
+            Duplicate(v) => write!(f, "multiple definitions of `{}`", v),
+            Missing(v) => write!(f, "undefined reference to `{}`", v),
+            Conflict(v) => write!(f, "conflicting types for `{}`", v),
+        }
+    }
+}
+
+impl Program {
+    // Parses every file as its own translation unit, then links them together
+    pub fn from_files(paths: &[String]) -> Result<Self> {
+        let mut units = vec![];
+        for path in paths {
+            let file = std::fs::read_to_string(path)
+                .map_err(|e| Diagnostic::new(format!("couldn't read `{}`: {}", path, e)))?;
+            units.push(Self::parse_unit(path, &file)?);
+        }
+
+        Self::link(units)
+    }
+
+    pub(crate) fn parse_unit(name: &str, src: &str) -> Result<Self> {
+        scope(true, || Self::parse_file(name, src))
+    }
+
+    // Parses a file into whatever scope is current, as `#include` does
+    pub(crate) fn parse_file(name: &str, src: &str) -> Result<Self> {
+        set_file(name);
+        let pair = CParser::parse(Rule::translation_unit, src)?.next().unwrap();
+        Self::parse(pair)
+    }
+
+    pub fn link(mut units: Vec<Self>) -> Result<Self> {
+        // Built-in headers are linked in once each, like any other unit
+        let mut headers = BTreeSet::new();
+        let mut i = 0;
+        while i < units.len() {
+            for h in units[i].includes.clone() {
+                if headers.insert(h.clone()) {
+                    let Some(src) = builtin_header(&h) else {
+                        return Err(LinkError::UnknownHeader(h).into());
+                    };
+                    units.push(Self::parse_unit(&format!("<{}>", h), src)?);
+                }
+            }
+            i += 1;
+        }
+
+        let mut linked = Self::default();
+        for (n, mut unit) in units.into_iter().enumerate() {
+            unit.internalize(n);
+
+            for (f, def) in unit.funs {
+                if linked.funs.contains_key(&f) {
+                    return Err(LinkError::Duplicate(f).into());
+                }
+                linked.funs.insert(f, def);
+            }
+
+            for (v, decl) in unit.vars {
+                match linked.vars.get(&v) {
+                    Some((_, prev, _)) if !same_object(prev, &decl.1) => {
+                        return Err(LinkError::Conflict(v).into());
+                    }
+                    Some(prev) if defines(prev) && defines(&decl) => {
+                        return Err(LinkError::Duplicate(v).into());
+                    }
+                    Some(prev) if defines(prev) => (),
+                    _ => {
+                        linked.vars.insert(v, decl);
+                    }
+                }
+            }
+
+            linked.order.extend(unit.order);
+            linked.protos.extend(unit.protos);
+        }
+
+        // Objects & functions share one namespace
+        for (v, decl) in &linked.vars {
+            if defines(decl) && linked.funs.contains_key(v) {
+                return Err(LinkError::Duplicate(v.clone()).into());
+            }
+        }
+
+        // Every declaration of a function must agree with its definition
+        let mut sigs: HashMap<&Ident, DType> = linked
+            .funs
+            .iter()
+            .map(|(f, (_, ret, ps, _))| {
+                let ps = ps.iter().map(|(t, _)| t.clone()).collect();
+                (f, DType::Function(ps, ret.clone().into()))
+            })
+            .collect();
+
+        // Those without a parameter list only have a return type to agree on
+        let (prototyped, unprototyped): (Vec<_>, Vec<_>) =
+            linked.protos.iter().partition(|(_, _, _, p)| *p);
+        for (f, _, ty, _) in prototyped {
+            match sigs.get(f) {
+                Some(sig) if !sig.compatible(ty) => {
+                    return Err(LinkError::Conflict(f.clone()).into());
+                }
+                Some(_) => (),
+                None => {
+                    sigs.insert(f, ty.clone());
+                }
+            }
+        }
+        for (f, _, ty, _) in unprototyped {
+            if let (Some(DType::Function(_, a)), DType::Function(_, b)) = (sigs.get(f), ty)
+                && !a.compatible(b)
+            {
+                return Err(LinkError::Conflict(f.clone()).into());
+            }
+        }
+
+        if !linked.funs.contains_key("main") {
+            return Err(LinkError::Missing("main".into()).into());
+        }
+
+        // Declarations need a definition somewhere, but only once they are used
+        let mut used = HashSet::new();
+        linked.visit_globals(&mut |v| {
+            used.insert(v.clone());
+        });
+
+        let vars = linked.vars.iter().filter(|(_, decl)| !defines(decl));
+        let protos = linked.protos.iter().map(|(f, _, _, _)| f);
+        for v in vars.map(|(v, _)| v).chain(protos) {
+            if !linked.funs.contains_key(v) && used.contains(v) {
+                return Err(LinkError::Missing(v.clone()).into());
+            }
+        }
+
+        Ok(linked)
+    }
+
+    // Renames `static` symbols, so they can't clash with those of other units
+    fn internalize(&mut self, unit: usize) {
+        let vars = self.vars.iter().map(|(v, (s, _, _))| (v, s));
+        let funs = self.funs.iter().map(|(f, (s, _, _, _))| (f, s));
+        let protos = self.protos.iter().map(|(f, s, _, _)| (f, s));
+
+        let statics: HashMap<Ident, Ident> = vars
+            .chain(funs)
+            .chain(protos)
+            .filter(|(_, s)| **s == Storage::Static)
+            .map(|(v, _)| (v.clone(), format!("{}#{}", v, unit)))
+            .collect();
+
+        if statics.is_empty() {
+            return;
+        }
+
+        let rename = |v: Ident| statics.get(&v).cloned().unwrap_or(v);
+
+        self.visit_globals(&mut |v| {
+            *v = rename(v.clone());
+        });
+
+        self.vars = self.vars.drain().map(|(v, d)| (rename(v), d)).collect();
+        self.funs = self.funs.drain().map(|(f, d)| (rename(f), d)).collect();
+        self.order = self.order.drain(..).map(rename).collect();
+        for (f, _, _, _) in &mut self.protos {
+            *f = rename(f.clone());
+        }
+    }
+
+    // Visits every use of a file-scope name
+    fn visit_globals(&mut self, f: &mut impl FnMut(&mut Ident)) {
+        for (_, _, def) in self.vars.values_mut() {
+            if let Some(def) = def {
+                def.visit_idents(f);
+            }
+        }
+
+        for (_, _, params, body) in self.funs.values_mut() {
+            let params = params.iter().filter_map(|(_, p)| p.clone()).collect();
+            body.visit_free_idents(&mut vec![params], f);
+        }
+    }
+}
+
+// Whether two declarations of an object agree on its type. An array's size
+// may be left out of all but one.
+pub(crate) fn same_object(a: &DType, b: &DType) -> bool {
+    use DType::*;
+    match (a.unqualified(), b.unqualified()) {
+        (Array(_, a) | Unsized(a), Unsized(b)) | (Unsized(a), Array(_, b)) => a.compatible(b),
+        _ => a.compatible(b),
+    }
+}
+
+// Whether a declaration is also a definition
+fn defines((s, _, def): &VDef) -> bool {
+    def.is_some() || *s != Storage::Extern
+}
@@ -5,6 +5,8 @@ pub mod declarator;
 pub mod definition;
 pub mod dtype;
 pub mod expr;
+pub mod intrinsic;
+pub mod linker;
 pub mod op;
 pub mod program;
 pub mod statement;
@@ -13,39 +15,42 @@ pub use declarator::*;
 pub use definition::*;
 pub use dtype::*;
 pub use expr::*;
+pub use linker::*;
 pub use op::*;
 pub use program::*;
 pub use statement::*;
 
 pub type Ident = String;
 
-pub trait ASTNode {
-    fn parse(pair: Pair<Rule>) -> Self;
+pub trait ASTNode: Sized {
+    fn parse(pair: Pair<Rule>) -> Result<Self>;
 
-    fn compile(&self, _context: &mut CompileContext) {
+    fn compile(&self, _context: &mut CompileContext) -> Result<()> {
         unimplemented!()
     }
 }
 
 // Useful for trashing inputs
 impl ASTNode for () {
-    fn parse(_: Pair<Rule>) -> Self {}
+    fn parse(_: Pair<Rule>) -> Result<Self> {
+        Ok(())
+    }
 }
 
 // Useful for reading strings
 impl ASTNode for String {
-    fn parse(pair: Pair<Rule>) -> Self {
-        pair.as_str().into()
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
+        Ok(pair.as_str().into())
     }
 }
 
 impl<T: ASTNode> ASTNode for Box<T> {
-    fn parse(pair: Pair<Rule>) -> Self {
-        T::parse(pair).into()
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
+        Ok(T::parse(pair)?.into())
     }
 
-    fn compile(&self, context: &mut CompileContext) {
-        (**self).compile(context);
+    fn compile(&self, context: &mut CompileContext) -> Result<()> {
+        (**self).compile(context)
     }
 }
 
@@ -71,7 +76,7 @@ macro_rules! parser_rule {
 
                         $(
                             let $nonterm = $nonterm.clone();
-                            let mut $nonterm = ASTNode::parse($nonterm);
+                            let mut $nonterm = ASTNode::parse($nonterm)?;
                             $(
                                 let mut $nonterm : $ty = $nonterm;
                             )?
@@ -81,14 +86,14 @@ macro_rules! parser_rule {
                             let mut $v2 = $v2.into_iter().cloned();
                         )?
 
-                        $out
+                        Ok($out)
                     })+
 
-                    _ => unimplemented!("Unable to match `{}`", $pair)
+                    _ => Err(Diagnostic::at($pair.as_span(), "unsupported syntax")),
                 }
             )*
 
-            r => unimplemented!("Rule `{:?}` not matched.", r)
+            r => Err(Diagnostic::at($pair.as_span(), format!("unexpected {:?}", r))),
         }
     }
 }
@@ -23,9 +23,9 @@ pub enum BinOp {
 }
 
 impl ASTNode for BinOp {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use BinOp::*;
-        match pair.as_str() {
+        Ok(match pair.as_str() {
             "+" => Add,
             "-" => Sub,
             "*" => Mul,
@@ -45,7 +45,7 @@ impl ASTNode for BinOp {
             "|" => Or,
             "^" => Xor,
             s => unimplemented!("Unknown op `{}`", s),
-        }
+        })
     }
 }
 
@@ -57,24 +57,26 @@ pub enum MonOp {
     SizeOf,
     Deref,
     Negate,
+    Plus,
     BinaryNot,
     AddrOf,
 }
 
 impl ASTNode for MonOp {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use MonOp::*;
-        match pair.as_str() {
+        Ok(match pair.as_str() {
             "!" => LogicalNot,
             "++" => Inc,
             "--" => Dec,
             "sizeof" => SizeOf,
             "*" => Deref,
             "-" => Negate,
+            "+" => Plus,
             "~" => BinaryNot,
             "&" => AddrOf,
             _ => unreachable!(),
-        }
+        })
     }
 }
 
@@ -94,9 +96,9 @@ pub enum AssignOp {
 }
 
 impl ASTNode for AssignOp {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use AssignOp::*;
-        match pair.as_str() {
+        Ok(match pair.as_str() {
             "=" => Assign,
             "*=" => MulAssign,
             "/=" => DivAssign,
@@ -109,6 +111,26 @@ impl ASTNode for AssignOp {
             "|=" => OrAssign,
             "^=" => XorAssign,
             _ => unreachable!(),
-        }
+        })
+    }
+}
+
+impl AssignOp {
+    // Operator applied by a compound assignment, e.g. `+` for `+=`
+    pub fn binop(&self) -> Option<BinOp> {
+        use AssignOp::*;
+        Some(match self {
+            Assign => return None,
+            MulAssign => BinOp::Mul,
+            DivAssign => BinOp::Div,
+            ModAssign => BinOp::Mod,
+            PlusAssign => BinOp::Add,
+            SubAssign => BinOp::Sub,
+            LShiftAssign => BinOp::LShift,
+            RShiftAssign => BinOp::RShift,
+            AndAssign => BinOp::And,
+            OrAssign => BinOp::Or,
+            XorAssign => BinOp::Xor,
+        })
     }
 }
@@ -2,59 +2,124 @@ use super::*;
 
 use std::collections::*;
 
-pub type FDef = (DType, Vec<ParamDecl>, Box<Stmt>);
-pub type VDef = (bool, DType, Option<Expr>);
+pub type FDef = (Storage, DType, Vec<ParamDecl>, Box<Stmt>);
+pub type VDef = (Storage, DType, Option<Expr>);
 
+#[derive(Default)]
 pub struct Program {
     pub funs: HashMap<Ident, FDef>,
     pub vars: HashMap<Ident, VDef>,
     pub order: Vec<Ident>, // Order of variables. Probably could be cleaner.
+    pub protos: Vec<(Ident, Storage, DType, bool)>, // Function declarations, & if prototyped
+    pub includes: BTreeSet<String>, // Built-in headers, linked in as separate units
 }
 
 impl ASTNode for Program {
-    fn parse(pair: Pair<Rule>) -> Self {
-        let mut funs = HashMap::default();
-        let mut vars = HashMap::default();
-        let mut order = vec![];
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
+        let mut program = Self::default();
 
         // Remove SOI & EOI
         use Rule::EOI;
         let decls = pair.into_inner().filter(|r| r.as_rule() != EOI);
 
-        for decl in decls.map(Defn::parse) {
-            use Defn::*;
-            match decl {
-                FDef(f, ty, ps, d) => {
-                    funs.insert(f, (ty, ps, d));
-                }
-                Vars(s, ty, vs) => {
-                    for (vd, def) in vs {
-                        let n = vd.get_name().expect("Unnamed variable");
-                        let ty = vd.set_type(ty.clone());
+        // Where each function & initialized variable is defined
+        let mut defined = HashMap::<Ident, Span>::new();
+        let mut define = |n: &Ident, span: pest::Span| match defined.get(n) {
+            Some(prev) => {
+                let e = Diagnostic::at(span, format!("redefinition of `{}`", n));
+                Err(e.note(prev.clone(), "previous definition is here"))
+            }
+            None => {
+                defined.insert(n.clone(), Span::from(span));
+                Ok(())
+            }
+        };
 
-                        if def.is_some() {
-                            order.push(n.clone());
-                        }
+        let mut defns = vec![];
+        for pair in decls {
+            let span = pair.as_span();
+            defns.push(Defn::parse(pair)?);
+            while let Some(defn) = defns.pop() {
+                use Defn::*;
+                match defn {
+                    FDef(s, f, ty, ps, mut d) => {
+                        define(&f, span)?;
+                        // Its `static` & `extern` locals are declared here too
+                        defns.extend(d.take_file_scoped());
+                        program.funs.insert(f, (s, ty, ps, d));
+                    }
+                    Vars(s, ty, vs) => {
+                        for (vd, def) in vs {
+                            let Some(n) = vd.get_name() else {
+                                return Err(Diagnostic::at(
+                                    span,
+                                    "declaration does not declare anything",
+                                ));
+                            };
+                            let ty = vd.set_type(ty.clone());
+
+                            if let DType::Function(_, _) = ty {
+                                program.protos.push((n, s, ty, !vd.is_unprototyped()));
+                                continue;
+                            }
+
+                            // Every declaration of an object must agree on its type
+                            if let Some((_, prev, _)) = program.vars.get(&n)
+                                && !same_object(prev, &ty)
+                            {
+                                return Err(Diagnostic::at(
+                                    span,
+                                    LinkError::Conflict(n).to_string(),
+                                ));
+                            }
 
-                        // Avoid overwriting definition with declaration
-                        if !vars.contains_key(&n) || def.is_some() {
-                            vars.insert(n, (s, ty, def));
+                            if def.is_some() {
+                                define(&n, span)?;
+                                program.order.push(n.clone());
+                            }
+
+                            // Compound literals at file scope live in global storage
+                            for (ty, v) in def.iter().flat_map(|d| d.compound_literals()) {
+                                let v = v.unwrap();
+                                program.vars.insert(v, (Storage::Static, ty, None));
+                            }
+
+                            // Avoid overwriting definition with declaration, but
+                            // let a tentative one define what `extern` only declared
+                            let tentative = s != Storage::Extern
+                                && matches!(program.vars.get(&n), Some((Storage::Extern, _, None)));
+                            if !program.vars.contains_key(&n) || def.is_some() || tentative {
+                                program.vars.insert(n, (s, ty, def));
+                            }
+                        }
+                    }
+                    Include(h) => {
+                        // The header's enumeration constants are needed to parse
+                        // the rest of this file, before the header is linked in
+                        if let Some(src) = builtin_header(&h) {
+                            let file = current_file();
+                            Self::parse_file(&format!("<{}>", h), src)?;
+                            set_file(&file);
                         }
+                        program.includes.insert(h);
                     }
+                    _ => return Err(Diagnostic::at(span, "unsupported declaration")),
                 }
-                _ => unreachable!(),
             }
         }
 
-        Self { funs, vars, order }
+        Ok(program)
     }
 
-    fn compile(&self, ctxt: &mut CompileContext) {
+    fn compile(&self, ctxt: &mut CompileContext) -> Result<()> {
         use StackInst::*;
         let init_lbl = ctxt.label(); // Always equal to 1
 
+        // Address 0 is never handed out, so no object compares equal to NULL
+        ctxt.global_offset = 1;
+
         // Declarations
-        for (f, (ret, args, _)) in &self.funs {
+        for (f, (_, ret, args, _)) in &self.funs {
             let ty = DType::Function(
                 args.iter().map(|(t, _)| t.clone()).collect(),
                 ret.clone().into(),
@@ -62,13 +127,41 @@ impl ASTNode for Program {
             ctxt.fdecl(f.clone(), ty);
         }
 
+        // Only `const` globals whose address is taken need any storage
+        let mut addressed = HashSet::new();
+        let mut find = |e: &Expr| {
+            if let Expr::Unary(MonOp::AddrOf, e) = e
+                && let Expr::Var(v) = &**e
+            {
+                addressed.insert(v.clone());
+            }
+        };
+        for (_, _, _, body) in self.funs.values() {
+            body.visit_exprs(&mut find);
+        }
+        for def in self.vars.values().filter_map(|(_, _, def)| def.as_ref()) {
+            def.visit(&mut find);
+        }
+
         for v in &self.order {
-            let (_, ty, _) = &self.vars[v];
+            let (_, ty, def) = &self.vars[v];
+
+            // `const` scalars with constant initializers are folded into their reads
+            if ty.is_const()
+                && ty.is_arithmetic()
+                && let Some((c, _)) = def.as_ref().and_then(|d| d.const_eval(Some(ctxt)))
+            {
+                ctxt.consts.insert(v.clone(), (ty.wrap(c), ty.clone()));
+                if !addressed.contains(v) {
+                    continue;
+                }
+            }
+
             ctxt.global_decl(v, ty);
         }
 
         for (v, (_, ty, _)) in &self.vars {
-            if !self.order.contains(v) && !matches!(ty, DType::Function(_, _)) {
+            if !self.order.contains(v) {
                 ctxt.global_decl(v, ty);
             }
         }
@@ -78,21 +171,27 @@ impl ASTNode for Program {
         // Allocate space for globals
         ctxt.emit(Alloc(ctxt.global_offset));
 
+        // Initializers run in the order they were written, so they can read
+        // globals initialized before them
         ctxt.stack_height = Some(ctxt.global_offset);
         for v in &self.order {
-            let (_, _, e) = &self.vars[v];
-            let def = e.clone().unwrap();
+            let (_, ty, def) = &self.vars[v];
+            let Some(def) = def.as_ref().filter(|_| ctxt.globals.contains_key(v)) else {
+                continue;
+            };
 
-            ctxt.emit(Comment(v.clone().leak()));
-            ctxt.compile(&def);
-            let addr = ctxt.globals[v].0 as usize;
-            ctxt.emit(LclStr(self.order.len() - addr));
+            ctxt.emit(Comment(v.clone()));
+            ctxt.check_implicit(ty, def);
+            def.compile_init(ty, ctxt)?;
+            ctxt.store(v)?;
         }
 
         // Call main()
         ctxt.stack_height = Some(0);
         let ret_lbl = ctxt.label();
-        let main_lbl = ctxt.funcs.get("main").unwrap().0;
+        let Some(&(main_lbl, _)) = ctxt.funcs.get("main") else {
+            return Err(Diagnostic::new("no `main` function"));
+        };
         ctxt.emit_stream(&[
             Push(ret_lbl),
             Push(ctxt.global_offset as Word + 1),
@@ -102,12 +201,124 @@ impl ASTNode for Program {
         ]);
         ctxt.emit(Exit);
 
-        // Definitions
-        for (f, (_, ps, b)) in &self.funs {
-            ctxt.fdef(f, ps, b);
+        // Definitions. An error only stops its own function from compiling.
+        for (f, (_, _, ps, b)) in &self.funs {
+            if let Err(e) = ctxt.fdef(f, ps, b) {
+                ctxt.errors.push(e);
+            }
         }
 
         // Label 0 is always Exit
         ctxt.emit(Label(0));
+        Ok(())
+    }
+}
+
+impl Program {
+    // Parses, links & compiles the C files among `args`, which may also toggle
+    // warnings, limit the stack with `-fstack-limit=<words>`, or optimize with
+    // `-O<level>`. A single `.ir` file is read as stack IR instead. Returns no
+    // code if there were any errors.
+    pub fn build(args: &[String]) -> (Option<Vec<StackInst>>, Vec<Diagnostic>) {
+        let mut ctxt = CompileContext::default();
+        let mut paths = vec![];
+        let mut level = 0;
+        for arg in args {
+            if let Some(l) = arg.strip_prefix("-O") {
+                let Ok(l) = (if l.is_empty() { Ok(1) } else { l.parse() }) else {
+                    let e = Diagnostic::new(format!("invalid optimization level `{}`", arg));
+                    return (None, vec![e]);
+                };
+                level = l;
+                continue;
+            }
+
+            if let Some(limit) = arg.strip_prefix("-fstack-limit=") {
+                let Ok(limit) = limit.parse() else {
+                    let e = Diagnostic::new(format!("invalid stack limit `{}`", limit));
+                    return (None, vec![e]);
+                };
+                ctxt.stack_limit = Some(limit);
+                continue;
+            }
+
+            if !arg.starts_with("-W") {
+                paths.push(arg.clone());
+                continue;
+            }
+
+            match Warning::from_flag(arg) {
+                Some((w, true)) => ctxt.disabled.remove(&w),
+                Some((w, false)) => ctxt.disabled.insert(w),
+                None => {
+                    let e = Diagnostic::new(format!("unknown warning `{}`", arg));
+                    return (None, vec![e]);
+                }
+            };
+        }
+
+        // IR is already compiled, so it runs as it is
+        if let Some(ir) = paths.iter().find(|p| p.ends_with(".ir")) {
+            if paths.len() > 1 {
+                let e = Diagnostic::new(format!("`{}` can't be linked with other files", ir));
+                return (None, vec![e]);
+            }
+            let stream = std::fs::read_to_string(ir)
+                .map_err(|e| Diagnostic::new(format!("couldn't read `{}`: {}", ir, e)))
+                .and_then(|text| {
+                    set_file(ir);
+                    parse_stack_ir(&text)
+                })
+                .and_then(|stream| Self::finish(stream, level));
+            return match stream {
+                Ok(stream) => (Some(stream), vec![]),
+                Err(e) => (None, vec![e]),
+            };
+        }
+
+        let program = match Self::from_files(&paths) {
+            Ok(program) => program,
+            Err(e) => return (None, vec![e]),
+        };
+
+        if let Err(e) = program.compile(&mut ctxt) {
+            ctxt.errors.push(e);
+        }
+
+        // Functions are compiled in no particular order
+        let mut diagnostics = ctxt.warnings;
+        diagnostics.extend(ctxt.errors.iter().cloned());
+        diagnostics.sort_by_key(|d| d.span.as_ref().map(|s| (s.file.clone(), s.line, s.col)));
+
+        if !ctxt.errors.is_empty() {
+            return (None, diagnostics);
+        }
+
+        // Only a bug in the compiler could make this fail
+        match Self::finish(ctxt.stream, level) {
+            Ok(stream) => (Some(stream), diagnostics),
+            Err(e) => {
+                diagnostics.push(e);
+                (None, diagnostics)
+            }
+        }
+    }
+
+    // Checks the code, then optimizes it, checking that nothing broke
+    fn finish(mut stream: Vec<StackInst>, level: u8) -> Result<Vec<StackInst>> {
+        verify_stack_ir(&stream)?;
+        optimize_stack_ir(&mut stream, level);
+        verify_stack_ir(&stream)?;
+        Ok(stream)
+    }
+}
+
+// Headers shipped with the compiler, found in `src/include`
+pub fn builtin_header(name: &str) -> Option<&'static str> {
+    match name {
+        "assert.h" => Some(include_str!("../include/assert.h")),
+        "stdbool.h" => Some(include_str!("../include/stdbool.h")),
+        "stdlib.h" => Some(include_str!("../include/stdlib.h")),
+        _ => None,
     }
 }
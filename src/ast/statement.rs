@@ -1,5 +1,7 @@
 use super::*;
 
+use std::collections::HashSet;
+
 type Label = String;
 
 #[derive(Debug)]
@@ -21,22 +23,28 @@ pub enum Stmt {
     Break,
     Return(Option<Expr>),
     Print(Expr),
+    ExitStmt(Expr),
+    Abort,
+    Assert(Expr, String),     // Along with the source of the condition
+    Spanned(Span, Box<Stmt>), // Where the statement is, for diagnostics
 }
 
 impl ASTNode for Stmt {
-    fn parse(pair: Pair<Rule>) -> Self {
+    fn parse(pair: Pair<Rule>) -> Result<Self> {
         use Stmt::*;
         parser_rule! {
             pair:
 
             stmt
-            | selection_stmt
+                [s] -> Spanned(pair.as_span().into(), s);
+
+            selection_stmt
             | iteration_stmt
             | jump_stmt
                 [s] -> s;
 
             declaration
-                [d] -> DefnStmt(d);
+                [d] -> Spanned(pair.as_span().into(), DefnStmt(d).into());
 
             labeled_stmt
                 [s] -> s;
@@ -49,11 +57,23 @@ impl ASTNode for Stmt {
                 [s] -> Default(s);
 
             compound_stmt
-                [.. ss,] -> SeqStmt(ss.map(Self::parse).collect());
+                [.. ss,] -> {
+                    let ss = scope(false, || ss.map(Self::parse).collect::<Result<_>>());
+                    SeqStmt(ss?)
+                };
 
             print_stmt
                 [e] -> Print(e);
 
+            exit_stmt
+                [e] -> ExitStmt(e);
+
+            abort_stmt
+                [] -> Abort;
+
+            assert_stmt
+                [e] -> Assert(e, pair.clone().into_inner().as_str().into());
+
             expr_stmt
                 [] -> ExprStmt(None);
                 [e] -> ExprStmt(Some(e));
@@ -102,46 +122,96 @@ impl ASTNode for Stmt {
         }
     }
 
-    fn compile(&self, ctxt: &mut CompileContext) {
+    fn compile(&self, ctxt: &mut CompileContext) -> Result<()> {
         let height = ctxt.stack_height;
         use StackInst::*;
         use Stmt::*;
         match self {
             DefnStmt(d) => {
-                let Defn::Vars(false, _, defs) = d else {
+                // `static` & `extern` ones were taken out to file scope
+                let Defn::Vars(Storage::Plain, base_ty, defs) = d else {
                     unreachable!();
                 };
 
+                for v in defs.iter().filter_map(|(decl, _)| decl.get_name()) {
+                    ctxt.declare(&v);
+                }
+                for v in defs.iter().flat_map(|(decl, _)| decl.size_vars()) {
+                    ctxt.mark_read(&v);
+                }
+
                 for (decl, def) in defs {
                     let Some(def) = def else { continue };
                     let Some(v) = decl.get_name() else { continue };
 
-                    ctxt.compile(def);
-                    ctxt.store(&v);
+                    let ty = decl.set_type(base_ty.clone());
+                    ctxt.check_implicit(&ty, def);
+                    def.compile_init(&ty, ctxt)?;
+                    ctxt.store(&v)?;
                 }
             }
             ExprStmt(Some(expr)) => {
-                ctxt.compile(expr);
-                ctxt.emit(Dealloc(1));
+                ctxt.compile(expr)?;
+                ctxt.emit(Dealloc(ctxt.stack_height.unwrap() - height.unwrap()));
             }
             ExprStmt(None) => {}
             SeqStmt(stmts) => {
+                let mut returned = false;
                 for stmt in stmts {
-                    ctxt.compile(stmt);
+                    // Labels can still be jumped to
+                    if returned && !stmt.is_labeled() {
+                        let span = stmt.span().or(ctxt.span.clone());
+                        ctxt.warn_at(Warning::Unreachable, span, "unreachable statement".into());
+                        returned = false;
+                    }
+
+                    ctxt.compile(stmt)?;
+                    returned |= stmt.returns();
                 }
             }
 
             Print(expr) => {
-                ctxt.compile(expr);
+                ctxt.compile(expr)?;
+                ctxt.convert(&expr.ty(ctxt), &DType::S16);
                 ctxt.emit(PutChar);
             }
 
+            ExitStmt(expr) => {
+                let lbl = ctxt.label();
+                ctxt.compile(expr)?;
+                ctxt.convert(&expr.ty(ctxt), &DType::S16);
+                ctxt.emit_stream(&[Exit, Label(lbl)]);
+                ctxt.stack_height = height;
+            }
+            Abort => {
+                let lbl = ctxt.label();
+                ctxt.emit_stream(&[Push(ABORT_STATUS), Exit, Label(lbl)]);
+                ctxt.stack_height = height;
+            }
+            Assert(cond, text) => {
+                let ok = ctxt.label();
+                let fail = ctxt.label();
+
+                ctxt.compile_cond(cond)?;
+                ctxt.emit_stream(&[Branch(ok, fail), Label(fail)]);
+
+                let msg = match &ctxt.span {
+                    Some(s) => format!("{}:{}: assertion `{}` failed\n", s.file, s.line, text),
+                    None => format!("assertion `{}` failed\n", text),
+                };
+                ctxt.trap(TRAP_ASSERT, &msg);
+                ctxt.emit(Label(ok));
+            }
+
             Return(e) => {
                 let lbl = ctxt.label();
                 if let Some(expr) = e {
-                    ctxt.compile(expr);
+                    let ty = ctxt.ret_ty.clone();
+                    ctxt.check_implicit(&ty, expr);
+                    ctxt.compile(expr)?;
+                    ctxt.convert(&expr.ty(ctxt), &ty);
                 } else {
-                    ctxt.emit(Push(0));
+                    ctxt.zeros(ctxt.ret_words);
                 }
                 ctxt.emit_stream(&[Push(ctxt.ret_lbl), Goto, Label(lbl)]);
             }
@@ -149,9 +219,9 @@ impl ASTNode for Stmt {
                 let t_lbl = ctxt.label();
                 let e_lbl = ctxt.label();
 
-                ctxt.compile(cond);
+                ctxt.compile_cond(cond)?;
                 ctxt.emit_stream(&[Branch(t_lbl, e_lbl), Label(t_lbl)]);
-                ctxt.compile(body);
+                ctxt.compile(body)?;
                 ctxt.emit_stream(&[Push(e_lbl), Goto, Label(e_lbl)]);
             }
             IfElseStmt(cond, t_body, f_body) => {
@@ -159,11 +229,11 @@ impl ASTNode for Stmt {
                 let f_lbl = ctxt.label();
                 let e_lbl = ctxt.label();
 
-                ctxt.compile(cond);
+                ctxt.compile_cond(cond)?;
                 ctxt.emit_stream(&[Branch(t_lbl, f_lbl), Label(t_lbl)]);
-                ctxt.compile(t_body);
+                ctxt.compile(t_body)?;
                 ctxt.emit_stream(&[Push(e_lbl), Goto, Label(f_lbl)]);
-                ctxt.compile(f_body);
+                ctxt.compile(f_body)?;
                 ctxt.emit_stream(&[Push(e_lbl), Goto, Label(e_lbl)]);
             }
             While(cond, body) => {
@@ -174,9 +244,9 @@ impl ASTNode for Stmt {
                 ctxt.loop_exit = (c_lbl, leave);
 
                 ctxt.emit_stream(&[Push(c_lbl), Goto, Label(c_lbl)]);
-                ctxt.compile(cond);
+                ctxt.compile_cond(cond)?;
                 ctxt.emit_stream(&[Branch(t_lbl, leave), Label(t_lbl)]);
-                ctxt.compile(body);
+                ctxt.compile(body)?;
                 ctxt.emit_stream(&[Push(c_lbl), Goto, Label(leave)]);
 
                 ctxt.loop_exit = old_loop_exit;
@@ -184,20 +254,23 @@ impl ASTNode for Stmt {
             For(init, cond, end, body) => {
                 let c_lbl = ctxt.label();
                 let b_lbl = ctxt.label();
+                let s_lbl = ctxt.label();
                 let leave = ctxt.label();
 
+                // `continue` still runs the step
                 let old_loop_exit = ctxt.loop_exit;
-                ctxt.loop_exit = (c_lbl, leave);
+                ctxt.loop_exit = (s_lbl, leave);
 
-                ctxt.compile(init);
+                ctxt.compile(init)?;
                 ctxt.emit_stream(&[Push(c_lbl), Goto, Label(c_lbl)]);
                 match cond {
-                    Some(cond) => ctxt.compile(cond),
+                    Some(cond) => ctxt.compile_cond(cond)?,
                     None => ctxt.emit(Push(1)),
                 }
                 ctxt.emit_stream(&[Branch(b_lbl, leave), Label(b_lbl)]);
-                ctxt.compile(body);
-                ctxt.compile(&ExprStmt(end.clone()));
+                ctxt.compile(body)?;
+                ctxt.emit_stream(&[Push(s_lbl), Goto, Label(s_lbl)]);
+                ctxt.compile(&ExprStmt(end.clone()))?;
                 ctxt.emit_stream(&[Push(c_lbl), Goto, Label(leave)]);
 
                 ctxt.loop_exit = old_loop_exit;
@@ -211,13 +284,20 @@ impl ASTNode for Stmt {
                 ctxt.loop_exit = (c_lbl, leave);
 
                 ctxt.emit_stream(&[Push(l_lbl), Goto, Label(l_lbl)]);
-                ctxt.compile(stmt);
+                ctxt.compile(stmt)?;
                 ctxt.emit_stream(&[Push(c_lbl), Goto, Label(c_lbl)]);
-                ctxt.compile(cond);
+                ctxt.compile_cond(cond)?;
                 ctxt.emit_stream(&[Branch(l_lbl, leave), Label(leave)]);
 
                 ctxt.loop_exit = old_loop_exit;
             }
+            // Label 0 is the exit, so outside of any loop there's nowhere to go
+            Break if ctxt.loop_exit.1 == 0 => {
+                return Err(ctxt.diagnostic("break statement not within loop or switch"));
+            }
+            Continue if ctxt.loop_exit.0 == 0 => {
+                return Err(ctxt.diagnostic("continue statement not within loop"));
+            }
             Break => {
                 let lbl = ctxt.label();
                 ctxt.emit_stream(&[Push(ctxt.loop_exit.1), Goto, Label(lbl)]);
@@ -226,9 +306,88 @@ impl ASTNode for Stmt {
                 let lbl = ctxt.label();
                 ctxt.emit_stream(&[Push(ctxt.loop_exit.0), Goto, Label(lbl)]);
             }
-            _ => todo!(),
+            SwitchStmt(e, body) => {
+                let leave = ctxt.label();
+                let ty = e.ty(ctxt);
+                let ty = ty.common(&ty);
+                let words = words(&ty);
+                let cases: Vec<_> = body
+                    .cases()
+                    .into_iter()
+                    .map(|c| (c, ctxt.label()))
+                    .collect();
+
+                // Compare against every case in turn
+                ctxt.compile(e)?;
+                ctxt.convert(&e.ty(ctxt), &ty);
+                let mut default = None;
+                let mut seen = HashSet::new();
+                for (case, lbl) in &cases {
+                    let Some(case) = case else {
+                        if default.replace(*lbl).is_some() {
+                            ctxt.error("multiple `default` labels in one switch".into());
+                        }
+                        continue;
+                    };
+
+                    let Some((v, _)) = case.const_eval(Some(ctxt)) else {
+                        ctxt.error("case label is not an integer constant expression".into());
+                        continue;
+                    };
+
+                    let v = ty.wrap(v);
+                    if !seen.insert(v) {
+                        ctxt.error(format!("duplicate case value `{}`", v));
+                    }
+
+                    let hit = ctxt.label();
+                    let next = ctxt.label();
+                    ctxt.dup(words);
+                    ctxt.push_const(v, &ty);
+                    ctxt.arith(Eq, &ty);
+                    ctxt.emit_stream(&[Branch(hit, next), Label(hit)]);
+                    ctxt.emit_stream(&[Dealloc(words), Push(*lbl), Goto, Label(next)]);
+                    ctxt.stack_height = height.map(|h| h + words);
+                }
+                let dead = ctxt.label();
+                ctxt.emit_stream(&[
+                    Dealloc(words),
+                    Push(default.unwrap_or(leave)),
+                    Goto,
+                    Label(dead),
+                ]);
+
+                let old_cases = std::mem::replace(
+                    &mut ctxt.cases,
+                    cases.iter().rev().map(|(_, lbl)| *lbl).collect(),
+                );
+                let old_loop_exit = ctxt.loop_exit;
+                ctxt.loop_exit.1 = leave;
+
+                ctxt.compile(body)?;
+                ctxt.emit_stream(&[Push(leave), Goto, Label(leave)]);
+
+                ctxt.cases = old_cases;
+                ctxt.loop_exit = old_loop_exit;
+            }
+            Case(_, s) | Default(s) => {
+                let Some(lbl) = ctxt.cases.pop() else {
+                    return Err(ctxt.diagnostic("case label not within a switch"));
+                };
+                ctxt.emit_stream(&[Push(lbl), Goto, Label(lbl)]);
+                ctxt.compile(s)?;
+            }
+            Spanned(span, s) => {
+                let outer = ctxt.span.replace(span.clone());
+                ctxt.compile(s)?;
+                ctxt.span = outer;
+            }
+            Labeled(_, _) | GotoStmt(_) => {
+                return Err(ctxt.diagnostic("`goto` is not supported yet"));
+            }
         }
         ctxt.stack_height = height;
+        Ok(())
     }
 }
 
@@ -236,7 +395,7 @@ impl Stmt {
     // TODO: Local scoping
     pub fn vars(&self) -> Vec<(DType, Option<Ident>)> {
         use Stmt::*;
-        match self {
+        let mut vars = match self {
             DefnStmt(d) => {
                 let Defn::Vars(_, base_ty, decls) = d else {
                     unreachable!()
@@ -245,22 +404,246 @@ impl Stmt {
                 decls
                     .iter()
                     .map(|(d, _)| (d.set_type(base_ty.clone()), d.get_name()))
+                    .filter(|(ty, _)| !matches!(ty, DType::Function(_, _)))
                     .collect()
             }
-            Print(_) | GotoStmt(_) | Continue | Break | Return(_) | ExprStmt(_) => vec![],
+            Print(_)
+            | GotoStmt(_)
+            | Continue
+            | Break
+            | Return(_)
+            | ExprStmt(_)
+            | ExitStmt(_)
+            | Abort
+            | Assert(_, _) => vec![],
             SwitchStmt(_, stmt)
             | While(_, stmt)
             | DoWhile(stmt, _)
             | IfStmt(_, stmt)
             | Default(stmt)
             | Case(_, stmt)
-            | Labeled(_, stmt) => stmt.vars(),
+            | Labeled(_, stmt)
+            | Spanned(_, stmt) => stmt.vars(),
             SeqStmt(stmts) => stmts.iter().flat_map(|s| s.vars()).collect(),
             For(s1, _, _, s2) | IfElseStmt(_, s1, s2) => {
                 let mut vs = s1.vars();
                 vs.extend(s2.vars());
                 vs
             }
+        };
+
+        for e in self.exprs() {
+            vars.extend(e.compound_literals());
+        }
+        vars
+    }
+
+    // Visits every expression in this statement, & in those inside it
+    pub fn visit_exprs(&self, f: &mut impl FnMut(&Expr)) {
+        use Stmt::*;
+        for e in self.exprs() {
+            e.visit(f);
+        }
+        match self {
+            Labeled(_, s) | Default(s) | Spanned(_, s) | Case(_, s) | SwitchStmt(_, s) => {
+                s.visit_exprs(f)
+            }
+            IfStmt(_, s) | While(_, s) | DoWhile(s, _) => s.visit_exprs(f),
+            IfElseStmt(_, s1, s2) | For(s1, _, _, s2) => {
+                s1.visit_exprs(f);
+                s2.visit_exprs(f);
+            }
+            SeqStmt(stmts) => {
+                for stmt in stmts {
+                    stmt.visit_exprs(f);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Expressions of this statement itself, & not of those inside it
+    fn exprs(&self) -> Vec<&Expr> {
+        use Stmt::*;
+        match self {
+            DefnStmt(Defn::Vars(_, _, defs)) => {
+                defs.iter().filter_map(|(_, d)| d.as_ref()).collect()
+            }
+            ExprStmt(Some(e)) | Return(Some(e)) | Print(e) | ExitStmt(e) | Assert(e, _) => vec![e],
+            Case(e, _)
+            | SwitchStmt(e, _)
+            | IfStmt(e, _)
+            | IfElseStmt(e, _, _)
+            | While(e, _)
+            | DoWhile(_, e) => vec![e],
+            For(_, cond, end, _) => [cond, end].into_iter().flatten().collect(),
+            _ => vec![],
         }
     }
+
+    // Whether control can never reach the end of this statement
+    pub fn returns(&self) -> bool {
+        use Stmt::*;
+        match self {
+            Return(_) | ExitStmt(_) | Abort => true,
+            // Unless a label after the return can be jumped to
+            SeqStmt(stmts) => stmts
+                .iter()
+                .rev()
+                .find(|s| s.returns() || s.is_labeled())
+                .is_some_and(|s| s.returns()),
+            IfElseStmt(_, t, f) => t.returns() && f.returns(),
+            Labeled(_, s) | Case(_, s) | Default(s) | Spanned(_, s) | DoWhile(s, _) => s.returns(),
+            // Every value jumps to some case, & none can leave
+            SwitchStmt(_, s) => s.cases().iter().any(Option::is_none) && !s.breaks() && s.returns(),
+            // Infinite loops
+            While(c, s) => c.const_eval(None).is_some_and(|(c, _)| c != 0) && !s.breaks(),
+            For(_, None, _, s) => !s.breaks(),
+            _ => false,
+        }
+    }
+
+    // Whether this statement breaks out of the loop or switch around it
+    fn breaks(&self) -> bool {
+        use Stmt::*;
+        match self {
+            Break => true,
+            Labeled(_, s) | Case(_, s) | Default(s) | Spanned(_, s) | IfStmt(_, s) => s.breaks(),
+            IfElseStmt(_, t, f) => t.breaks() || f.breaks(),
+            SeqStmt(stmts) => stmts.iter().any(|s| s.breaks()),
+            _ => false,
+        }
+    }
+
+    fn is_labeled(&self) -> bool {
+        use Stmt::*;
+        match self {
+            Labeled(_, _) | Case(_, _) | Default(_) => true,
+            Spanned(_, s) => s.is_labeled(),
+            _ => false,
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Stmt::Spanned(span, _) => Some(span.clone()),
+            _ => None,
+        }
+    }
+
+    // Case labels of a switch with this body, in order. `None` is `default`.
+    fn cases(&self) -> Vec<Option<&Expr>> {
+        use Stmt::*;
+        match self {
+            Case(e, s) => [Some(e)].into_iter().chain(s.cases()).collect(),
+            Default(s) => [None].into_iter().chain(s.cases()).collect(),
+            Labeled(_, s) | Spanned(_, s) | While(_, s) | DoWhile(s, _) | IfStmt(_, s) => s.cases(),
+            For(s1, _, _, s2) | IfElseStmt(_, s1, s2) => {
+                let mut cs = s1.cases();
+                cs.extend(s2.cases());
+                cs
+            }
+            SeqStmt(stmts) => stmts.iter().flat_map(|s| s.cases()).collect(),
+            // Nested switches have their own cases
+            _ => vec![],
+        }
+    }
+
+    // Takes out the declarations of `static` & `extern` objects, which live
+    // at file scope
+    pub fn take_file_scoped(&mut self) -> Vec<Defn> {
+        use Stmt::*;
+        match self {
+            DefnStmt(Defn::Vars(s, _, _)) if *s != Storage::Plain => {
+                let DefnStmt(d) = std::mem::replace(self, ExprStmt(None)) else {
+                    unreachable!()
+                };
+                vec![d]
+            }
+            Labeled(_, s)
+            | Default(s)
+            | Spanned(_, s)
+            | Case(_, s)
+            | SwitchStmt(_, s)
+            | IfStmt(_, s)
+            | While(_, s)
+            | DoWhile(s, _) => s.take_file_scoped(),
+            IfElseStmt(_, s1, s2) | For(s1, _, _, s2) => {
+                let mut ds = s1.take_file_scoped();
+                ds.extend(s2.take_file_scoped());
+                ds
+            }
+            SeqStmt(stmts) => stmts
+                .iter_mut()
+                .flat_map(|s| s.take_file_scoped())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    // Visits every identifier in an expression of this statement that isn't
+    // declared by a block around it. `scopes` has the names each enclosing
+    // block declares so far, innermost last.
+    pub fn visit_free_idents(
+        &mut self,
+        scopes: &mut Vec<HashSet<Ident>>,
+        f: &mut impl FnMut(&mut Ident),
+    ) {
+        use Stmt::*;
+        match self {
+            DefnStmt(Defn::Vars(s, ty, defs)) => {
+                for (decl, def) in defs {
+                    // Block-scope functions & `extern`s name file-scope ones
+                    let local = *s != Storage::Extern
+                        && !matches!(decl.set_type(ty.clone()), DType::Function(_, _));
+                    if let Some(v) = decl.get_name().filter(|_| local) {
+                        scopes.last_mut().unwrap().insert(v);
+                    }
+                    if let Some(def) = def {
+                        visit_free(def, scopes, f);
+                    }
+                }
+            }
+            DefnStmt(_) | GotoStmt(_) | Continue | Break | ExprStmt(None) | Return(None)
+            | Abort => (),
+            ExprStmt(Some(e)) | Return(Some(e)) | Print(e) | ExitStmt(e) | Assert(e, _) => {
+                visit_free(e, scopes, f)
+            }
+            Labeled(_, s) | Default(s) | Spanned(_, s) => s.visit_free_idents(scopes, f),
+            Case(e, s) | SwitchStmt(e, s) | IfStmt(e, s) | While(e, s) | DoWhile(s, e) => {
+                visit_free(e, scopes, f);
+                s.visit_free_idents(scopes, f);
+            }
+            IfElseStmt(c, t, e) => {
+                visit_free(c, scopes, f);
+                t.visit_free_idents(scopes, f);
+                e.visit_free_idents(scopes, f);
+            }
+            // What the first clause declares is visible to the rest
+            For(init, cond, end, body) => {
+                scopes.push(HashSet::new());
+                init.visit_free_idents(scopes, f);
+                for e in [cond, end].into_iter().flatten() {
+                    visit_free(e, scopes, f);
+                }
+                body.visit_free_idents(scopes, f);
+                scopes.pop();
+            }
+            SeqStmt(stmts) => {
+                scopes.push(HashSet::new());
+                for stmt in stmts {
+                    stmt.visit_free_idents(scopes, f);
+                }
+                scopes.pop();
+            }
+        }
+    }
+}
+
+fn visit_free(e: &mut Expr, scopes: &[HashSet<Ident>], f: &mut impl FnMut(&mut Ident)) {
+    e.visit_idents(&mut |v| {
+        if !scopes.iter().any(|s| s.contains(v)) {
+            f(v)
+        }
+    });
 }
@@ -3,7 +3,12 @@ use std::io::Read;
 
 use super::*;
 
-pub fn exec_bf(bf: &[BF]) {
+// Once the program halts, the head is on the 0 label, just above the exit status
+fn exit_status(stack: &[Word], head: usize) -> Word {
+    head.checked_sub(1).map_or(0, |h| stack[h])
+}
+
+pub fn exec_bf(bf: &[BF]) -> Word {
     let fast = bf.iter().cloned().map(FastBF::from).collect::<Vec<_>>();
 
     let map = parse_bracs(&fast);
@@ -14,13 +19,16 @@ pub fn exec_bf(bf: &[BF]) {
 
     while ip < bf.len() {
         use BF::*;
-        match bf[ip] {
+        match &bf[ip] {
             Profile(_) => (),
             Dbg(_msg) => {
                 #[cfg(feature = "debugbf")]
                 {
                     dbg!(head);
                     dbg!(_msg);
+                    if _msg == BREAKPOINT {
+                        pause();
+                    }
                 }
             }
             Left => head -= 1,
@@ -51,9 +59,12 @@ pub fn exec_bf(bf: &[BF]) {
         }
         ip += 1;
     }
+
+    exit_status(&stack, head)
 }
 
-pub fn exec_fastbf(fast: &[FastBF]) -> HashMap<StackInst, usize> {
+// Returns the exit status, along with how many steps each instruction took
+pub fn exec_fastbf(fast: &[FastBF]) -> (Word, HashMap<StackInst, usize>) {
     let map = parse_bracs(fast);
     let mut ip = 0;
     let mut stack = vec![0 as Word];
@@ -63,11 +74,14 @@ pub fn exec_fastbf(fast: &[FastBF]) -> HashMap<StackInst, usize> {
     let mut profile = HashMap::new();
 
     while ip < fast.len() {
-        *profile.entry(inst).or_insert(0) += 1;
+        match profile.get_mut(&inst) {
+            Some(steps) => *steps += 1,
+            None => _ = profile.insert(inst.clone(), 1),
+        }
 
         use FastBF::*;
         match &fast[ip] {
-            Inst(i) => inst = *i,
+            Inst(i) => inst = i.clone(),
             Move(n) => {
                 head = (head as isize).wrapping_add(*n) as usize;
                 if head >= stack.len() {
@@ -161,7 +175,7 @@ pub fn exec_fastbf(fast: &[FastBF]) -> HashMap<StackInst, usize> {
         ip += 1;
     }
 
-    profile
+    (exit_status(&stack, head), profile)
 }
 
 fn parse_bracs(code: &[FastBF]) -> HashMap<usize, usize> {
@@ -99,6 +99,12 @@ fn convert(bf: &[BF]) -> Vec<FastBF> {
 
     let mut i = 0;
     'outer: while i < bf.len() {
+        // Debug info is dropped from release builds
+        if let BF::Dbg(_) = bf[i] {
+            i += 1;
+            continue;
+        }
+
         for (inst, fast) in SNIPPETS.iter().cloned() {
             let mut snippet = vec![];
             emit_bf(inst, &mut snippet);
@@ -109,7 +115,7 @@ fn convert(bf: &[BF]) -> Vec<FastBF> {
                 continue 'outer;
             }
         }
-        fast_code.push(bf[i].into());
+        fast_code.push(bf[i].clone().into());
         i += 1;
     }
 
@@ -122,7 +128,7 @@ impl From<BF> for FastBF {
         use BF::*;
         match value {
             Profile(p) => Inst(p),
-            Dbg(_) => unimplemented!(),
+            Dbg(_) => Inst(StackInst::Nop),
             Left => Move(-1),
             Right => Move(1),
             Dec => Const(-1),
@@ -2,9 +2,9 @@ use std::iter::repeat_n;
 
 use super::*;
 
-#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub enum BF {
-    Dbg(&'static str),
+    Dbg(String),
     Profile(StackInst),
     Left,   // `<`
     Right,  // `>`
@@ -37,7 +37,7 @@ impl BF {
         out
     }
 
-    pub fn show(self) -> char {
+    pub fn show(&self) -> char {
         use BF::*;
         match self {
             Left => '<',
@@ -86,7 +86,7 @@ pub fn translate(stack: &[StackInst]) -> Vec<BF> {
         use BF::*;
 
         // Profiling, to optimize fastbf
-        bf.push(Profile(inst));
+        bf.push(Profile(inst.clone()));
         emit_bf(inst, &mut bf);
     }
 
@@ -101,7 +101,12 @@ pub fn emit_bf(inst: StackInst, bf: &mut Vec<BF>) {
     match inst {
         Push(b) => {
             bf.push(Right);
-            bf.extend(repeat_n(Inc, b as _));
+            // Cells wrap, so large values are quicker to count down to
+            if b > Word::MAX / 2 {
+                bf.extend(repeat_n(Dec, b.wrapping_neg() as _));
+            } else {
+                bf.extend(repeat_n(Inc, b as _));
+            }
         }
         Swap => bf.extend(BF::parse(
             "
@@ -137,29 +142,29 @@ pub fn emit_bf(inst: StackInst, bf: &mut Vec<BF>) {
         LclRead(n) => {
             let left = repeat_n(Left, n).collect::<Vec<_>>();
             let right = repeat_n(Right, n).collect::<Vec<_>>();
-            bf.extend(&left);
+            bf.extend(left.iter().cloned());
             bf.extend(BF::parse("[-"));
-            bf.extend(&right);
+            bf.extend(right.iter().cloned());
             bf.extend(BF::parse(">+>+<<")); // Make 2 copies
-            bf.extend(&left);
+            bf.extend(left.iter().cloned());
             bf.extend(BF::parse("]"));
-            bf.extend(&right);
+            bf.extend(right.iter().cloned());
             bf.extend(BF::parse(">>[-<<")); // Move 1 copy back
-            bf.extend(&left);
+            bf.extend(left.iter().cloned());
             bf.extend(BF::parse("+"));
-            bf.extend(&right);
+            bf.extend(right.iter().cloned());
             bf.extend(BF::parse(">>]<"))
         }
         LclStr(n) => {
             let left = repeat_n(Left, n).collect::<Vec<_>>();
             let right = repeat_n(Right, n).collect::<Vec<_>>();
-            bf.extend(&left);
+            bf.extend(left.iter().cloned());
             bf.extend(BF::parse("[-]")); // Erase previous value
-            bf.extend(&right);
+            bf.extend(right.iter().cloned());
             bf.extend(BF::parse("[-")); // Enter move loop
-            bf.extend(&left);
+            bf.extend(left.iter().cloned());
             bf.extend(BF::parse("+")); // Shift 1 unit over
-            bf.extend(&right);
+            bf.extend(right.iter().cloned());
             bf.extend(BF::parse("]<")); // Exit loop and move stack head
         }
         Label(n) if n != 0 => {
@@ -275,10 +280,13 @@ pub fn emit_bf(inst: StackInst, bf: &mut Vec<BF>) {
             ",
         )),
         Div => bf.extend(BF::parse(
-            // From https://esolangs.org/wiki/Brainfuck_algorithms#Divmod
+            // Based on https://esolangs.org/wiki/Brainfuck_algorithms#Divmod,
+            // but counting each step first, so a divisor of 1 works too
             "
-            // Execute
-            <[->-[>+>>]>[+[-<+>]>+>>]<<<<<]
+            // Prepare state
+            >[-]>[-]>[-]>[-]>[-]<<<<<<
+            // Execute: x y count quotient
+            [->->+<[>>>>]>[[-<+>]>+>>>]<<<<<<]
             // Return value
             >[-]>[-]>[-<<<+>>>]<<<
             ",
@@ -343,7 +351,19 @@ pub fn emit_bf(inst: StackInst, bf: &mut Vec<BF>) {
         }
         Goto => bf.extend(BF::parse(">]")),
         PutChar => bf.extend(BF::parse(".[-]<")),
-        Label(0) | Nop | Debug(_) | Comment(_) => {}
+        Bf(code, _, _) => bf.extend(BF::parse(&code)),
+        Debug(l) => bf.push(Dbg(l)),
+        Trap(code) => {
+            // Report it, then stop the way `Exit` does
+            for c in format!("\nerror: {}\n", trap_message(code)).bytes() {
+                emit_bf(Push(c as Word), bf);
+                emit_bf(PutChar, bf);
+            }
+            for inst in [Push(ABORT_STATUS), Push(0), Goto] {
+                emit_bf(inst, bf);
+            }
+        }
+        Label(0) | Nop | Comment(_) => {}
         i => todo!("{:?}", i),
     }
 }
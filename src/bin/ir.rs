@@ -0,0 +1,15 @@
+use c2bf::*;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    let (stream, diagnostics) = Program::build(&args[1..]);
+    for d in diagnostics {
+        eprintln!("{}", d);
+    }
+    let Some(stream) = stream else {
+        std::process::exit(1);
+    };
+
+    print!("{}", show_stack_ir(&stream));
+}
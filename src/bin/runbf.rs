@@ -3,27 +3,21 @@ use c2bf::*;
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
-    let file = &std::fs::read_to_string(&args[1]).unwrap();
+    let (stream, diagnostics) = Program::build(&args[1..]);
+    for d in diagnostics {
+        eprintln!("{}", d);
+    }
+    let Some(stream) = stream else {
+        std::process::exit(1);
+    };
 
-    let pair = CParser::parse(Rule::translation_unit, file)
-        .unwrap()
-        .next()
-        .unwrap();
-
-    let parsed = Program::parse(pair);
-
-    let mut ctxt = CompileContext::default();
-
-    parsed.compile(&mut ctxt);
-
-    let CompileContext { stream, .. } = ctxt;
-
-    dbg!(&stream);
+    eprint!("{}", show_stack_ir(&stream));
 
     let transpilation = translate(&stream);
     println!("{}", show_bf(&transpilation, cfg!(feature = "debugbf")));
 
     println!("\nExecution:\n");
 
-    exec_bf(&transpilation);
+    let status = exec_bf(&transpilation);
+    std::process::exit(status as i32);
 }
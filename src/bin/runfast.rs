@@ -3,22 +3,15 @@ use c2bf::*;
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
-    let file = &std::fs::read_to_string(&args[1]).unwrap();
-
-    let pair = CParser::parse(Rule::translation_unit, file)
-        .unwrap()
-        .next()
-        .unwrap();
-
-    let parsed = Program::parse(pair);
-
-    let mut ctxt = CompileContext::default();
-
-    parsed.compile(&mut ctxt);
-
-    let CompileContext { stream, .. } = ctxt;
+    let (stream, diagnostics) = Program::build(&args[1..]);
+    for d in diagnostics {
+        eprintln!("{}", d);
+    }
+    let Some(stream) = stream else {
+        std::process::exit(1);
+    };
 
-    dbg!(&stream);
+    eprint!("{}", show_stack_ir(&stream));
 
     let transpilation = translate(&stream);
     println!("{}", show_bf(&transpilation, cfg!(feature = "debugbf")));
@@ -27,7 +20,7 @@ fn main() {
 
     println!("\nExecution:\n");
 
-    let profile = exec_fastbf(&optimized);
+    let (status, profile) = exec_fastbf(&optimized);
 
     if cfg!(feature = "profile") {
         let mut sorted = profile.into_iter().collect::<Vec<_>>();
@@ -35,4 +28,6 @@ fn main() {
 
         println!("\n\nProfile:\n{:?}", sorted);
     }
+
+    std::process::exit(status as i32);
 }
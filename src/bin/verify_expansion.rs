@@ -3,26 +3,20 @@ use c2bf::*;
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
-    let file = &std::fs::read_to_string(&args[1]).unwrap();
-
-    let pair = CParser::parse(Rule::translation_unit, file)
-        .unwrap()
-        .next()
-        .unwrap();
-
-    let parsed = Program::parse(pair);
-
-    let mut ctxt = CompileContext::default();
-
-    parsed.compile(&mut ctxt);
-
-    let CompileContext { mut stream, .. } = ctxt;
+    let (stream, diagnostics) = Program::build(&args[1..]);
+    for d in diagnostics {
+        eprintln!("{}", d);
+    }
+    let Some(mut stream) = stream else {
+        std::process::exit(1);
+    };
 
     StackInst::expand(&mut stream);
 
-    dbg!(&stream);
+    eprint!("{}", show_stack_ir(&stream));
 
     println!("\nExecution:\n");
 
-    exec_stack_program(&stream);
+    let status = exec_stack_program(&stream);
+    std::process::exit(status as i32);
 }
@@ -0,0 +1,206 @@
+use super::*;
+
+use std::cell::RefCell;
+use std::fmt;
+
+thread_local! {
+    // Name of the file being parsed, so spans know where they come from
+    static FILE: RefCell<String> = RefCell::default();
+}
+
+pub fn set_file(name: &str) {
+    FILE.set(name.into());
+}
+
+pub fn current_file() -> String {
+    FILE.with_borrow(|f| f.clone())
+}
+
+// A location in the source, along with the line it is on
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+    pub len: usize, // Length of the highlight, within the first line
+    pub text: String,
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        let start = span.start_pos();
+        let (line, col) = start.line_col();
+        let text = start.line_of().trim_end_matches(['\r', '\n']).to_string();
+        let len = span.as_str().lines().next().unwrap_or("").len();
+
+        Span {
+            file: current_file(),
+            line,
+            col,
+            len: len.max(1),
+            text,
+        }
+    }
+}
+
+pub type Result<T, E = Diagnostic> = std::result::Result<T, E>;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Level {
+    #[default]
+    Error,
+    Warning,
+    Note,
+}
+
+// Suspicious code, which still compiles. Each can be toggled with `-W<name>`
+// & `-Wno-<name>`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Warning {
+    UnusedVariable,
+    MissingReturn,
+    Narrowing,
+    UnsignedCompare,
+    Unreachable,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 5] = [
+        Warning::UnusedVariable,
+        Warning::MissingReturn,
+        Warning::Narrowing,
+        Warning::UnsignedCompare,
+        Warning::Unreachable,
+    ];
+
+    pub fn name(self) -> &'static str {
+        use Warning::*;
+        match self {
+            UnusedVariable => "unused-variable",
+            MissingReturn => "return-type",
+            Narrowing => "narrowing",
+            UnsignedCompare => "type-limits",
+            Unreachable => "unreachable-code",
+        }
+    }
+
+    // Reads a toggle such as `-Wno-narrowing`, returning whether it enables the warning
+    pub fn from_flag(flag: &str) -> Option<(Self, bool)> {
+        let name = flag.strip_prefix("-W")?;
+        let (name, on) = match name.strip_prefix("no-") {
+            Some(name) => (name, false),
+            None => (name, true),
+        };
+
+        let w = Self::ALL.into_iter().find(|w| w.name() == name)?;
+        Some((w, on))
+    }
+}
+
+// An error or warning in the program being compiled
+#[derive(Clone, Default, Debug)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub span: Option<Span>,
+    pub notes: Box<[Diagnostic]>, // Other places involved, shown after this one
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Diagnostic {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn at(span: impl Into<Span>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            message: message.into(),
+            span: Some(span.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn note(mut self, span: impl Into<Span>, message: impl Into<String>) -> Self {
+        let mut notes = std::mem::take(&mut self.notes).into_vec();
+        notes.push(Diagnostic {
+            level: Level::Note,
+            ..Diagnostic::at(span, message)
+        });
+        self.notes = notes.into();
+        self
+    }
+}
+
+impl From<pest::error::Error<Rule>> for Diagnostic {
+    fn from(e: pest::error::Error<Rule>) -> Self {
+        use pest::error::LineColLocation::*;
+        let ((line, col), len) = match e.line_col {
+            Pos(p) => (p, 1),
+            Span(s, e) if s.0 == e.0 => (s, e.1 - s.1),
+            Span(s, _) => (s, 1),
+        };
+
+        Diagnostic {
+            level: Level::Error,
+            message: e.variant.message().into(),
+            span: Some(self::Span {
+                file: current_file(),
+                line,
+                col,
+                len: len.max(1),
+                text: e.line().into(),
+            }),
+            notes: Box::default(),
+        }
+    }
+}
+
+impl From<LinkError> for Diagnostic {
+    fn from(e: LinkError) -> Self {
+        Diagnostic::new(e.to_string())
+    }
+}
+
+// Shown the way rustc does:
+//
+// error: use of undeclared identifier `x`
+// --> main.c:3:3
+//   |
+// 3 |   x = 4;
+//   |   ^^^^^^
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+        };
+        writeln!(f, "{}: {}", level, self.message)?;
+
+        if let Some(span) = &self.span {
+            show_span(f, span)?;
+        }
+        for note in &self.notes {
+            write!(f, "{}", note)?;
+        }
+        Ok(())
+    }
+}
+
+fn show_span(f: &mut fmt::Formatter<'_>, span: &Span) -> fmt::Result {
+    let gutter = " ".repeat(span.line.to_string().len());
+    writeln!(f, "{}--> {}:{}:{}", gutter, span.file, span.line, span.col)?;
+    writeln!(f, "{} |", gutter)?;
+    writeln!(f, "{} | {}", span.line, span.text)?;
+
+    // Keep tabs, so the highlight lines up
+    let indent: String = span
+        .text
+        .chars()
+        .take(span.col - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    writeln!(f, "{} | {}{}", gutter, indent, "^".repeat(span.len))
+}
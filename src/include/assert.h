@@ -0,0 +1,5 @@
+// Diagnostics.
+//
+// `assert(cond)` is built into the compiler, so this header is empty. A failed
+// assertion prints the file, line & condition, then stops the program with
+// exit status 134, like `abort()`.
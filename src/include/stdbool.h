@@ -0,0 +1,6 @@
+// Booleans.
+//
+// `bool` is built in, as it is in C23, & is stored as `_Bool`: one cell,
+// holding 0 or 1. Any other value is converted to 1 when stored.
+
+enum { false, true };
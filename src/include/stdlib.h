@@ -0,0 +1,104 @@
+// Dynamic memory.
+//
+// The heap is a fixed arena which lives with the globals, at the bottom of the
+// tape, so it can never run into the stack growing above it. Every block
+// starts with a header cell holding `(length << 1) | used`, where the length
+// counts the header itself. Free neighbours are merged lazily by `malloc`.
+//
+// Running out of memory is deterministic: `malloc`, `calloc` & `realloc`
+// return 0 (NULL) when no free block is large enough.
+
+static unsigned __heap_size = 1024;
+static unsigned __heap[1024];
+
+void *malloc(unsigned n) {
+  // The header would wrap `need` around to 0
+  if (n >= 65535) {
+    return 0;
+  }
+
+  unsigned need = n + 1;
+  unsigned i = 0;
+
+  // The whole arena starts out as one free block
+  if (!__heap[0]) {
+    __heap[0] = __heap_size << 1;
+  }
+
+  while (i < __heap_size) {
+    unsigned len = __heap[i] >> 1;
+
+    if (!(__heap[i] & 1)) {
+      while (i + len < __heap_size && !(__heap[i + len] & 1)) {
+        len = len + (__heap[i + len] >> 1);
+      }
+
+      if (len >= need) {
+        // Split off the remainder
+        if (len > need) {
+          __heap[i + need] = (len - need) << 1;
+          len = need;
+        }
+
+        __heap[i] = (len << 1) | 1;
+        return &__heap[i + 1];
+      }
+
+      __heap[i] = len << 1;
+    }
+
+    i = i + len;
+  }
+
+  return 0;
+}
+
+void free(void *p) {
+  if (p) {
+    unsigned *block = p;
+    unsigned i = block - __heap - 1;
+    __heap[i] = __heap[i] & ~1;
+  }
+}
+
+void *calloc(unsigned n, unsigned size) {
+  if (size != 0 && n > 65535 / size) {
+    return 0;
+  }
+
+  unsigned total = n * size;
+  unsigned *cleared = malloc(total);
+
+  if (cleared) {
+    for (unsigned j = 0; j < total; j++) {
+      cleared[j] = 0;
+    }
+  }
+
+  return cleared;
+}
+
+void *realloc(void *p, unsigned n) {
+  if (!p) {
+    return malloc(n);
+  }
+
+  unsigned *old = p;
+  unsigned i = old - __heap - 1;
+  unsigned old_len = (__heap[i] >> 1) - 1;
+
+  if (n <= old_len) {
+    return p;
+  }
+
+  unsigned *moved = malloc(n);
+
+  if (moved) {
+    for (unsigned k = 0; k < old_len; k++) {
+      moved[k] = old[k];
+    }
+    free(p);
+  }
+
+  return moved;
+}
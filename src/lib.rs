@@ -1,10 +1,12 @@
 pub mod ast;
 pub mod bf;
+pub mod diagnostic;
 pub mod parser;
 pub mod stack;
 
 pub use ast::*;
 pub use bf::*;
+pub use diagnostic::*;
 pub use parser::*;
 pub use stack::*;
 
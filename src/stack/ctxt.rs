@@ -11,16 +11,28 @@ pub struct CompileContext {
     pub stack_height: Option<usize>,
     pub stream: Vec<StackInst>,
     pub ret_lbl: Label,
+    pub ret_ty: DType,             // Return type of the current function
+    pub ret_words: usize,          // Size of the current function's return value
     pub loop_exit: (Label, Label), // continue & break labels, respectively
+    pub cases: Vec<Label>,         // Labels of the enclosing switch's remaining cases
     pub funcs: HashMap<Ident, (Label, DType)>,
     pub globals: HashMap<Ident, (Word, DType)>,
+    pub consts: HashMap<Ident, (i128, DType)>, // `const` globals, folded into their reads
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+    pub disabled: HashSet<Warning>, // Warnings turned off with `-Wno-...`
+    pub span: Option<Span>,         // Statement being compiled, for diagnostics
+    pub stack_limit: Option<Word>,  // Words the stack may grow to, if checked
     locals: HashMap<Ident, (Word, DType)>,
+    declared: Vec<(Ident, Option<Span>)>, // Variables of the current function
+    read: HashSet<Ident>,                 // Variables whose value is ever used
+    max_height: usize,                    // Tallest the current function's stack has been
     label_count: Label,
 }
 
 impl CompileContext {
-    pub fn compile<T: ASTNode>(&mut self, n: &T) {
-        n.compile(self);
+    pub fn compile<T: ASTNode>(&mut self, n: &T) -> Result<()> {
+        n.compile(self)
     }
 
     pub fn label(&mut self) -> Label {
@@ -52,8 +64,14 @@ impl CompileContext {
         self.funcs[v].0
     }
 
-    pub fn call_fn(&mut self, v: &Expr, args: &Vec<Expr>) {
+    pub fn call_fn(&mut self, v: &Expr, args: &[Expr]) -> Result<()> {
         let height = self.stack_height.expect("Height should be known.");
+
+        if !self.check_call(v, args) {
+            // Keep compiling, so that later errors are reported too
+            self.emit(StackInst::Push(0));
+            return Ok(());
+        }
         let ret_label = self.label();
 
         use StackInst::*;
@@ -61,128 +79,667 @@ impl CompileContext {
         self.emit_stream(&[
             Push(ret_label),
             LclRead(height),
-            Push(self.local_offset as u16 + height as u16 - 1),
-            Add, // stack pointer = previous stack pointer + stack frame size
+            Push(height as Word + 1),
+            Add, // stack pointer = previous stack pointer + current height + return label
         ]);
 
-        for arg in args {
-            self.compile(arg);
+        let params = match v.ty(self).decayed().deref() {
+            Some(DType::Function(params, _)) => params,
+            _ => vec![],
+        };
+        for (arg, param) in args.iter().zip(params) {
+            self.compile(arg)?;
+            self.convert(&arg.ty(self), &param);
         }
 
-        self.compile(v);
+        let ret_words = match v.ty(self).decayed().deref() {
+            Some(DType::Function(_, ret)) => words(&ret),
+            _ => 1,
+        };
+
+        self.compile(v)?;
         self.emit_stream(&[Goto, Label(ret_label)]);
-        self.stack_height = Some(height + 1);
+        self.stack_height = Some(height + ret_words);
+        Ok(())
     }
 
-    pub fn push_addr(&mut self, v: &Ident) {
-        use StackInst::*;
+    // Checks arguments against the callee's parameter list
+    fn check_call(&mut self, v: &Expr, args: &[Expr]) -> bool {
+        let name = match v {
+            Expr::Var(f) => f.clone(),
+            _ => "function pointer".into(),
+        };
+
+        if let Expr::Var(f) = v
+            && self.vty(f).is_none()
+        {
+            self.error(format!("call to undeclared function `{}`", f));
+            return false;
+        }
 
-        if let Some((addr, _)) = self.globals.get(v) {
-            self.emit(Push(*addr));
-            return;
+        let Some(DType::Function(params, _)) = v.ty(self).decayed().deref() else {
+            self.error(format!("`{}` is not a function", name));
+            return false;
+        };
+
+        if params.len() != args.len() {
+            self.error(format!(
+                "`{}` takes {} argument(s), but {} were given",
+                name,
+                params.len(),
+                args.len()
+            ));
+            return false;
+        }
+
+        let mut ok = true;
+        for (i, (param, arg)) in params.iter().zip(args).enumerate() {
+            let ty = arg.ty(self);
+            let null = param.is_pointer() && matches!(arg, Expr::Const(0));
+            if !null && !param.accepts(&ty) {
+                self.error(format!(
+                    "argument {} of `{}` has type `{}`, expected `{}`",
+                    i + 1,
+                    name,
+                    ty,
+                    param
+                ));
+                ok = false;
+            } else {
+                self.check_narrowing(param, arg);
+            }
         }
 
+        ok
+    }
+
+    pub fn push_addr(&mut self, v: &Ident) -> Result<()> {
+        use StackInst::*;
+        self.read.insert(v.clone());
+
         if let Some((addr, _)) = self.locals.get(v) {
             let height = self.stack_height.unwrap();
             self.emit_stream(&[LclRead(height - 1), Push(*addr), Add]);
-            return;
+            return Ok(());
         }
 
-        unreachable!("{}", v);
+        if let Some((addr, _)) = self.globals.get(v) {
+            self.emit(Push(*addr));
+            return Ok(());
+        }
+
+        Err(self.undeclared(v))
     }
 
-    pub fn push_var(&mut self, v: &Ident) {
+    pub fn push_var(&mut self, v: &Ident) -> Result<()> {
         use StackInst::*;
+        self.read.insert(v.clone());
 
-        if let Some((addr, _)) = self.globals.get(v) {
+        // Locals hide globals
+        if let Some((addr, ty)) = self.locals.get(v) {
+            let height = self.stack_height.unwrap();
+            // Each word read moves both the top of the stack & the next word up by one
+            let offset = height - 1 - *addr as usize;
+            for _ in 0..ty.size() {
+                self.emit(LclRead(offset));
+            }
+            return Ok(());
+        }
+
+        if let Some((addr, ty)) = self.globals.get(v) {
+            let (addr, size) = (*addr, ty.size() as usize);
             let height = self.stack_height.unwrap();
             // If in global scope
             if self.ret_lbl == 0 {
-                self.emit(LclRead(height - *addr as usize - 1));
-            } else {
+                for _ in 0..size {
+                    self.emit(LclRead(height - addr as usize - 1));
+                }
+            } else if size == 1 {
                 self.emit_stream(&[
                     LclRead(height - 1),
                     Push(height as Word),
                     Add,
-                    Push(*addr),
+                    Push(addr),
                     Sub,
                     StkRead,
                 ]);
+            } else {
+                self.emit(Push(addr));
+                self.load(size);
             }
-            return;
+            return Ok(());
         }
 
         if let Some((addr, _)) = self.funcs.get(v) {
             self.emit(Push(*addr));
-            return;
+            return Ok(());
         }
 
-        if let Some((addr, _)) = self.locals.get(v) {
+        Err(self.undeclared(v))
+    }
+
+    pub fn store(&mut self, v: &Ident) -> Result<()> {
+        use StackInst::*;
+
+        if let Some((addr, ty)) = self.locals.get(v) {
+            let size = ty.size() as usize;
             let height = self.stack_height.unwrap();
-            let offset = height - 1 - *addr as usize;
-            self.emit_stream(&[LclRead(offset)]);
-            return;
+            let offset = height - size - *addr as usize;
+            for _ in 0..size {
+                self.emit(LclStr(offset));
+            }
+            return Ok(());
         }
 
-        unreachable!("{}", v);
+        if let Some((addr, ty)) = self.globals.get(v) {
+            let (addr, size) = (*addr, ty.size() as usize);
+            let height = self.stack_height.unwrap();
+            if size == 1 {
+                self.emit_stream(&[
+                    LclRead(height - 1),
+                    Push(height as Word - 1),
+                    Add,
+                    Push(addr),
+                    Sub,
+                    StkStr,
+                ]);
+            } else {
+                self.emit(Push(addr));
+                self.store_addr(size);
+            }
+            return Ok(());
+        }
+
+        Err(self.undeclared(v))
     }
 
-    pub fn store(&mut self, v: &Ident) {
+    // Replaces the address on top of the stack with the `n` words it points to
+    pub fn load(&mut self, n: usize) {
         use StackInst::*;
 
-        if let Some((addr, _)) = self.locals.get(v) {
+        if n == 1 {
             let height = self.stack_height.unwrap();
-            let offset = height - 1 - *addr as usize;
-            self.emit(LclStr(offset));
+            self.emit_stream(&[
+                LclRead(height - 1),
+                Push(height as Word - 1),
+                Add,
+                Swap,
+                Sub,
+                StkRead,
+            ]);
             return;
         }
 
-        if let Some((addr, _)) = self.globals.get(v) {
+        for i in 0..n {
+            self.emit_stream(&[LclRead(i), Push(i as Word), Add]);
+            self.load(1);
+        }
+        self.squash(1, n);
+    }
+
+    // Pops an address, then the `n` words to be stored there
+    pub fn store_addr(&mut self, n: usize) {
+        use StackInst::*;
+
+        if n == 1 {
             let height = self.stack_height.unwrap();
             self.emit_stream(&[
                 LclRead(height - 1),
-                Push(height as Word - 1),
+                Push(height as Word - 2),
                 Add,
-                Push(*addr),
+                Swap,
                 Sub,
                 StkStr,
             ]);
             return;
         }
 
-        unreachable!();
+        for i in 0..n {
+            self.emit_stream(&[LclRead(n - i), LclRead(1), Push(i as Word), Add]);
+            self.store_addr(1);
+        }
+        self.emit(Dealloc(n + 1));
+    }
+
+    // Copies the top `n` words of the stack
+    pub fn dup(&mut self, n: usize) {
+        use StackInst::*;
+        if n == 1 {
+            self.emit(Copy);
+            return;
+        }
+        for _ in 0..n {
+            self.emit(LclRead(n - 1));
+        }
+    }
+
+    pub fn zeros(&mut self, n: usize) {
+        for _ in 0..n {
+            self.emit(StackInst::Push(0));
+        }
+    }
+
+    // Multiplies the top of the stack, e.g. to turn an index into an offset
+    pub fn scale(&mut self, factor: Word) {
+        use StackInst::*;
+        if factor != 1 {
+            self.emit_stream(&[Push(factor), Mul]);
+        }
+    }
+
+    // Pushes a constant of type `ty`, lowest word first
+    pub fn push_const(&mut self, v: i128, ty: &DType) {
+        let v = ty.wrap(v);
+        for i in 0..words(ty) {
+            self.emit(StackInst::Push((v >> (Word::BITS as usize * i)) as Word));
+        }
+    }
+
+    // Converts the value on top of the stack between scalar types. Values are
+    // kept wrapped to their own type, so only narrowing takes any work, besides
+    // the words a wider type adds.
+    pub fn convert(&mut self, from: &DType, to: &DType) {
+        use DType::*;
+        use StackInst::*;
+        let (from, to) = (from.unqualified(), to.unqualified());
+        if from == to {
+            return;
+        }
+
+        let scalar = |t: &DType| t.is_arithmetic() || t.is_pointer();
+        let (n, m) = (words(&from.decayed()), words(&to.decayed()));
+        if *to == Bool {
+            self.truth(from);
+        } else if scalar(from) && scalar(to) && n > m {
+            // The low words come first, so the high ones are on top
+            self.emit(Dealloc(n - m));
+        } else if scalar(from) && scalar(to) && n < m {
+            if from.is_signed() {
+                self.emit(Copy);
+                self.is_negative();
+                self.emit(Negate);
+                for _ in 1..m - n {
+                    self.emit(Copy);
+                }
+            } else {
+                self.zeros(m - n);
+            }
+        }
+
+        match to {
+            Bool => self.emit_stream(&[LNot, LNot]),
+            U8 if *from != Bool => self.emit_stream(&[Push(256), Mod]),
+            // Wraps into -128..127
+            S8 if *from != Bool => {
+                self.emit_stream(&[Push(128), Add, Push(256), Mod, Push(128), Sub])
+            }
+            _ => (),
+        }
+    }
+
+    // Folds a value of type `ty` into one word, which is nonzero if it was
+    pub fn truth(&mut self, ty: &DType) {
+        for _ in 1..words(ty) {
+            self.emit(StackInst::Or);
+        }
+    }
+
+    // Compiles a condition, leaving one word for a `Branch`
+    pub fn compile_cond(&mut self, cond: &Expr) -> Result<()> {
+        self.compile(cond)?;
+        self.truth(&cond.ty(self));
+        Ok(())
+    }
+
+    // Emits a unary operator on an operand of type `ty`
+    pub fn unary(&mut self, op: StackInst, ty: &DType) {
+        use StackInst::*;
+        let n = words(ty);
+        match op {
+            LNot => {
+                self.truth(ty);
+                self.emit(LNot);
+            }
+            _ if n == 1 => self.emit(op),
+            Not => {
+                for _ in 0..n {
+                    self.emit_stream(&[LclRead(n - 1), Not]);
+                }
+                self.squash(n, n);
+            }
+            // -x is ~x + 1
+            Negate => {
+                self.unary(Not, ty);
+                self.push_const(1, ty);
+                self.arith(Add, ty);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Emits a binary operator on operands of type `ty`. The stack machine's
+    // operators are unsigned, so the signed ones are built out of them.
+    pub fn arith(&mut self, op: StackInst, ty: &DType) {
+        use StackInst::*;
+        if words(ty) > 1 {
+            self.arith_words(op, ty);
+            return;
+        }
+
+        if !ty.is_signed() {
+            self.emit(op);
+            return;
+        }
+
+        match op {
+            Lt | LtEq | Gr | GrEq => {
+                // Operands of different signs are ordered by their signs alone
+                let (diff, same, done) = (self.label(), self.label(), self.label());
+                let height = self.stack_height;
+                self.emit(LclRead(1));
+                self.is_negative();
+                self.emit(LclRead(1));
+                self.is_negative();
+                self.emit_stream(&[Neq, Branch(diff, same), Label(diff), Dealloc(1)]);
+                self.is_negative();
+                if let Gr | GrEq = op {
+                    self.emit(LNot);
+                }
+                self.emit_stream(&[Push(done), Goto, Label(same)]);
+                self.stack_height = height;
+                self.emit_stream(&[op, Push(done), Goto, Label(done)]);
+            }
+            Div | Mod => {
+                // Divides the magnitudes. A quotient is negative if exactly one
+                // operand is, & a remainder has the sign of the dividend.
+                self.emit(LclRead(1));
+                self.is_negative();
+                if op == Div {
+                    self.emit(LclRead(1));
+                    self.is_negative();
+                    self.emit(Neq);
+                }
+                self.emit_stream(&[LclRead(2), LclRead(2)]);
+                self.squash(2, 3);
+                self.abs();
+                self.emit(Swap);
+                self.abs();
+                self.emit_stream(&[Swap, op, Swap]);
+                self.negate_if();
+            }
+            RShift => {
+                // Shifts in copies of the sign bit, as `~(~a >> b)` does
+                self.emit(LclRead(1));
+                self.is_negative();
+                self.emit_stream(&[Negate, LclRead(2), LclRead(1), Xor, LclRead(2), RShift, Xor]);
+                self.squash(2, 1);
+            }
+            _ => self.emit(op),
+        }
+    }
+
+    // `arith` on values of several words. Each operand has its lowest word
+    // deepest, so a word's depth stays the same as the result is pushed.
+    fn arith_words(&mut self, op: StackInst, ty: &DType) {
+        use StackInst::*;
+        let n = words(ty);
+        match op {
+            // Each word carries into the next, which is kept on top
+            Add => {
+                self.emit(Push(0));
+                for _ in 0..n {
+                    self.emit_stream(&[LclRead(2 * n), LclRead(n + 1), Add]);
+                    // The sum wrapped if it is below either part
+                    self.emit_stream(&[Copy, LclRead(n + 2), Lt]);
+                    self.emit_stream(&[LclRead(1), LclRead(3), Add]);
+                    self.emit_stream(&[Copy, LclRead(3), Lt, LclRead(2), Or]);
+                    self.squash(3, 2);
+                }
+                self.emit(Dealloc(1));
+                self.squash(2 * n, n);
+            }
+            // Each word borrows from the next, which is kept on top
+            Sub => {
+                self.emit(Push(0));
+                for _ in 0..n {
+                    self.emit_stream(&[LclRead(2 * n), LclRead(n + 1), Sub]);
+                    self.emit_stream(&[LclRead(2 * n + 1), LclRead(n + 2), Lt]);
+                    self.emit_stream(&[LclRead(1), LclRead(3), Lt, Or]);
+                    self.emit_stream(&[LclRead(1), LclRead(3), Sub, Swap]);
+                    self.squash(2, 2);
+                }
+                self.emit(Dealloc(1));
+                self.squash(2 * n, n);
+            }
+            And | Or | Xor => {
+                for _ in 0..n {
+                    self.emit_stream(&[LclRead(2 * n - 1), LclRead(n), op.clone()]);
+                }
+                self.squash(2 * n, n);
+            }
+            Eq | Neq => {
+                self.arith_words(Xor, ty);
+                self.truth(ty);
+                self.emit(LNot);
+                if op == Neq {
+                    self.emit(LNot);
+                }
+            }
+            // Goes up from the lowest word, where a difference in a higher
+            // word overrides any below it. Only the top word has a sign.
+            Lt | LtEq | Gr | GrEq => {
+                let swap = matches!(op, Gr | GrEq);
+                self.emit(Push(matches!(op, LtEq | GrEq) as Word));
+                for i in 0..n {
+                    let (a, b) = (2 * n - i, n - i);
+                    let (x, y) = if swap { (b, a) } else { (a, b) };
+                    // Flipping the sign bit orders signed words as unsigned ones
+                    let flip: &[_] = match i == n - 1 && ty.is_signed() {
+                        true => &[Push(1 << (Word::BITS - 1)), Xor],
+                        false => &[],
+                    };
+                    self.emit(LclRead(x));
+                    self.emit_stream(flip);
+                    self.emit(LclRead(y + 1));
+                    self.emit_stream(flip);
+                    self.emit(Lt);
+                    self.emit_stream(&[LclRead(a + 1), LclRead(b + 2), Eq]);
+                    self.emit_stream(&[LclRead(2), And, Or, LclStr(1)]);
+                }
+                self.squash(2 * n, 1);
+            }
+            _ => {
+                let name = match op {
+                    Mul => "*",
+                    Div => "/",
+                    Mod => "%",
+                    LShift => "<<",
+                    _ => ">>",
+                };
+                self.error(format!("unsupported operation `{}` on `{}`", name, ty));
+                self.emit(Dealloc(n));
+            }
+        }
+    }
+
+    // Replaces the top of the stack with whether it is negative. Doubling a
+    // word only wraps around if its sign bit is set.
+    fn is_negative(&mut self) {
+        use StackInst::*;
+        self.emit_stream(&[Copy, Copy, Add, Swap, Lt]);
+    }
+
+    fn abs(&mut self) {
+        self.emit(StackInst::Copy);
+        self.is_negative();
+        self.negate_if();
+    }
+
+    // Pops the top of the stack, & negates the word beneath it if it was nonzero
+    fn negate_if(&mut self) {
+        use StackInst::*;
+        let (neg, done) = (self.label(), self.label());
+        self.emit_stream(&[
+            Branch(neg, done),
+            Label(neg),
+            Negate,
+            Push(done),
+            Goto,
+            Label(done),
+        ]);
+    }
+
+    // Discards the `n` words beneath the top `m` words of the stack
+    pub fn squash(&mut self, n: usize, m: usize) {
+        use StackInst::*;
+        for i in 0..m {
+            self.emit_stream(&[LclRead(m - 1 - i), LclStr(n + m - i)]);
+        }
+        self.emit(Dealloc(n));
+    }
+
+    // Value of a `const` global, unless a local hides it
+    pub fn constant(&self, v: &Ident) -> Option<(i128, DType)> {
+        if self.locals.contains_key(v) {
+            return None;
+        }
+        self.consts.get(v).cloned()
     }
 
-    pub fn vty(&self, v: &Ident) -> &DType {
+    pub fn vty(&self, v: &Ident) -> Option<&DType> {
         if let Some((_, t)) = self.locals.get(v) {
-            return t;
+            return Some(t);
         }
 
         if let Some((_, t)) = self.globals.get(v) {
-            return t;
+            return Some(t);
+        }
+
+        if let Some((_, t)) = self.consts.get(v) {
+            return Some(t);
         }
 
         if let Some((_, t)) = self.funcs.get(v) {
-            return t;
+            return Some(t);
+        }
+
+        None
+    }
+
+    // An error at the statement being compiled
+    pub fn diagnostic(&self, msg: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            message: msg.into(),
+            span: self.span.clone(),
+            ..Default::default()
+        }
+    }
+
+    // Records an error, but carries on compiling to find more
+    pub fn error(&mut self, msg: String) {
+        self.errors.push(self.diagnostic(msg));
+    }
+
+    pub fn warn(&mut self, w: Warning, msg: String) {
+        self.warn_at(w, self.span.clone(), msg);
+    }
+
+    pub fn warn_at(&mut self, w: Warning, span: Option<Span>, msg: String) {
+        if self.disabled.contains(&w) {
+            return;
+        }
+
+        self.warnings.push(Diagnostic {
+            level: Level::Warning,
+            message: format!("{} [-W{}]", msg, w.name()),
+            span,
+            ..Default::default()
+        });
+    }
+
+    // Checks a value implicitly converted to `to`, as by `=` or `return`
+    pub fn check_implicit(&mut self, to: &DType, e: &Expr) {
+        let from = e.ty(self);
+        if let (Some(t), Some(f)) = (to.decayed().deref(), from.decayed().deref())
+            && f.is_const()
+            && !t.is_const()
+        {
+            self.error(format!(
+                "conversion from `{}` to `{}` discards `const`",
+                from, to
+            ));
+        }
+
+        self.check_narrowing(to, e);
+    }
+
+    // Warns when a value of type `from` gets implicitly converted to a smaller type
+    pub fn check_narrowing(&mut self, to: &DType, e: &Expr) {
+        let from = e.ty(self);
+        if *to.unqualified() == DType::Bool
+            || !to.is_arithmetic()
+            || !from.is_arithmetic()
+            || to.bits() >= from.bits()
+        {
+            return;
         }
 
-        unreachable!("{}", v)
+        // Constants are fine, as long as they fit
+        if let Some((v, _)) = e.const_eval(Some(self))
+            && to.wrap(v) == v
+        {
+            return;
+        }
+
+        self.warn(
+            Warning::Narrowing,
+            format!(
+                "implicit conversion from `{}` to `{}` may lose precision",
+                from, to
+            ),
+        );
+    }
+
+    // Prints a message, then stops the program with a `Trap`
+    pub fn trap(&mut self, code: Word, msg: &str) {
+        use StackInst::*;
+        for c in msg.bytes() {
+            self.emit_stream(&[Push(c as Word), PutChar]);
+        }
+        self.emit(Trap(code));
+    }
+
+    pub fn declare(&mut self, v: &Ident) {
+        self.declared.push((v.clone(), self.span.clone()));
+    }
+
+    pub fn mark_read(&mut self, v: &Ident) {
+        self.read.insert(v.clone());
+    }
+
+    fn undeclared(&self, v: &Ident) -> Diagnostic {
+        self.diagnostic(format!("use of undeclared identifier `{}`", v))
     }
 
-    pub fn fdef(&mut self, f: &Ident, params: &Vec<ParamDecl>, body: &Stmt) {
+    pub fn fdef(&mut self, f: &Ident, params: &Vec<ParamDecl>, body: &Stmt) -> Result<()> {
         // New Stack Frame
         self.ret_lbl = self.label();
+        self.span = None;
 
         self.locals.clear();
+        self.declared.clear();
+        self.read.clear();
         self.local_offset = 1; // Include stack pointer in stack frame
 
         // Param Declarations
         use StackInst::*;
         for (pty, pname) in params {
-            let Some(pname) = pname else { continue };
-
-            self.local_decl(pname, pty);
+            match pname {
+                Some(pname) => self.local_decl(pname, pty),
+                None => self.local_offset += pty.size() as usize,
+            }
         }
+        let param_words = self.local_offset - 1;
 
         // Allocate space for all variables
         for (vty, v) in body.vars() {
@@ -192,42 +749,109 @@ impl CompileContext {
         }
 
         let label = self.fn_label(f);
+        let Some(DType::Function(_, ret)) = self.vty(f) else {
+            unreachable!()
+        };
+        let ret = (**ret).clone();
+        self.ret_words = words(&ret);
+        self.ret_ty = ret;
+
         let frame_size = self.local_offset;
 
         self.emit_stream(&[
-            Comment(f.clone().leak()),
+            Comment(f.clone()),
             Label(label),
-            Alloc(frame_size - params.len() - 1), // Stack pointer is already allocated
+            Alloc(frame_size - param_words - 1), // Stack pointer is already allocated
         ]);
 
         self.stack_height = Some(frame_size);
 
-        self.compile(body);
+        // Stops runaway recursion before the new frame, & whatever gets
+        // pushed over it (e.g. arguments), outgrows the stack. How tall that
+        // is gets filled in once the body is compiled.
+        self.max_height = frame_size;
+        let mut height_at = None;
+        if let Some(limit) = self.stack_limit {
+            let (over, ok) = (self.label(), self.label());
+            self.emit(LclRead(frame_size - 1));
+            height_at = Some(self.stream.len());
+            self.emit_stream(&[Push(0), Add, Push(limit), Gr, Branch(over, ok), Label(over)]);
+            let name = f.split('#').next().unwrap(); // Without the unit of a `static`
+            let msg = format!("stack overflow in `{}`\n", name);
+            self.trap(TRAP_STACK_OVERFLOW, &msg);
+            self.emit(Label(ok));
+        }
+
+        self.compile(body)?;
+
+        if let Some(at) = height_at {
+            self.stream[at] = Push(self.max_height as Word);
+        }
 
         self.stack_height = None; // Ignore stack height from this point on.
-                                  // Return to caller, which should have pushed a return label
-        self.emit_stream(&[
-            Push(0),
-            Push(self.ret_lbl),
-            Goto,
-            Label(self.ret_lbl),
-            Move(frame_size),
-            Dealloc(frame_size),
-            Swap,
-            Goto,
-        ]);
+
+        for (v, span) in std::mem::take(&mut self.declared) {
+            if !self.read.contains(&v) {
+                let msg = format!("unused variable `{}`", v);
+                self.warn_at(Warning::UnusedVariable, span, msg);
+            }
+        }
+
+        // `main` returns 0 by default, like in C99
+        if self.ret_ty != DType::Void && f != "main" && !body.returns() {
+            let msg = format!("`{}` can reach its end without returning a value", f);
+            self.warn_at(Warning::MissingReturn, body.span(), msg);
+        }
+
+        // Falling off the end returns 0
+        for _ in 0..self.ret_words {
+            self.emit(Push(0));
+        }
+
+        // Return to caller, which should have pushed a return label
+        self.emit_stream(&[Push(self.ret_lbl), Goto, Label(self.ret_lbl)]);
+
+        if self.ret_words == 1 {
+            self.emit_stream(&[Move(frame_size), Dealloc(frame_size), Swap, Goto]);
+        } else {
+            // Bring the return label above the value, then drop the frame beneath both
+            self.emit(LclRead(frame_size + self.ret_words));
+            self.squash(frame_size + 1, self.ret_words + 1);
+            self.emit(Goto);
+        }
+
+        Ok(())
+    }
+
+    // Where the current function's variables are, as a label for `Debug`
+    pub fn dump_locals(&self) -> String {
+        let height = self.stack_height.unwrap();
+        let mut locals = self.locals.iter().collect::<Vec<_>>();
+        locals.sort_by_key(|(_, (addr, _))| *addr);
+
+        let locals = locals
+            .into_iter()
+            .map(|(v, (addr, _))| format!("{}@{}", v, height - 1 - *addr as usize))
+            .collect::<Vec<_>>();
+        format!("locals (depth from top): {}", locals.join(" "))
     }
 
     pub fn emit(&mut self, inst: StackInst) {
         if let (Some(height), (args, Some(output))) = (self.stack_height, inst.signature()) {
             self.stack_height = Some(height - args + output);
+            self.max_height = self.max_height.max(height).max(height - args + output);
         }
         self.stream.push(inst);
     }
 
     pub fn emit_stream(&mut self, code: &[StackInst]) {
-        for &inst in code {
-            self.emit(inst);
+        for inst in code {
+            self.emit(inst.clone());
         }
     }
 }
+
+// Words used to pass a value of this type around; `void` still takes a (dummy) word
+pub fn words(ty: &DType) -> usize {
+    (ty.size() as usize).max(1)
+}
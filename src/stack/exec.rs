@@ -1,11 +1,38 @@
 use std::collections::HashMap;
+use std::io::Read;
 
 use super::*;
 
-pub fn exec_stack_program(code: &[StackInst]) {
+// Runs the program, returning its exit status
+pub fn exec_stack_program(code: &[StackInst]) -> Word {
     let mut stack_machine = StackMachine::default();
 
-    stack_machine.exec(code);
+    match stack_machine.exec(code) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("\nerror: {}", e);
+            ABORT_STATUS
+        }
+    }
+}
+
+// A `Trap` which stopped the program
+#[derive(Debug)]
+pub struct TrapError {
+    pub code: Word,
+    pub ip: usize,
+}
+
+impl std::fmt::Display for TrapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at #{}", trap_message(self.code), self.ip)
+    }
+}
+
+// Waits for enter, at a breakpoint
+pub fn pause() {
+    eprint!("Paused at breakpoint, press enter to continue...");
+    let _ = std::io::stdin().read_line(&mut String::new());
 }
 
 #[derive(Default)]
@@ -15,7 +42,8 @@ pub struct StackMachine {
 }
 
 impl StackMachine {
-    pub fn exec(&mut self, code: &[StackInst]) {
+    // Returns the exit status, which is left on top of the stack
+    pub fn exec(&mut self, code: &[StackInst]) -> Result<Word, TrapError> {
         use StackInst::*;
         let labels: HashMap<Word, usize> = code
             .iter()
@@ -28,7 +56,7 @@ impl StackMachine {
 
         let mut ip = 0;
         loop {
-            let inst = code[ip];
+            let inst = code[ip].clone();
 
             let (args, _) = inst.signature();
             if self.stack.len() < args {
@@ -39,14 +67,19 @@ impl StackMachine {
                 Exit | Label(0) => {
                     break;
                 }
+                Trap(code) => return Err(TrapError { code, ip }),
                 Debug(l) => {
                     println!("Stack @ {}: {:?}", l, self.stack);
+                    if l == BREAKPOINT {
+                        pause();
+                    }
                 }
                 Nop | Label(_) | Comment(_) => (),
                 Push(b) => self.stack.push(b),
                 PutChar => {
                     print!("{}", self.stack.pop().unwrap() as u8 as char);
                 }
+                Bf(code, i, o) => self.exec_bf(&code, i, o, ip)?,
 
                 Alloc(n) => {
                     let len = self.stack.len();
@@ -182,5 +215,79 @@ impl StackMachine {
 
             ip += 1;
         }
+
+        Ok(self.stack.last().copied().unwrap_or(0))
+    }
+
+    // Runs inline BF on the stack, laid out on the tape the way `translate`
+    // does it: word `n` lives in cell `n + 1`, & the head starts on the top.
+    fn exec_bf(
+        &mut self,
+        code: &str,
+        inputs: usize,
+        outputs: usize,
+        ip: usize,
+    ) -> Result<(), TrapError> {
+        let trap = || TrapError {
+            code: TRAP_INLINE_BF,
+            ip,
+        };
+        let height = self.stack.len() - inputs + outputs;
+        let code = BF::parse(code);
+
+        let mut tape = vec![0 as Word];
+        tape.append(&mut self.stack);
+        let mut head = tape.len() - 1;
+
+        let mut bracs = vec![];
+        let mut ip = 0;
+        while ip < code.len() {
+            use BF::*;
+            match code[ip] {
+                Left => head = head.checked_sub(1).ok_or_else(trap)?,
+                Right => {
+                    head += 1;
+                    if tape.len() == head {
+                        tape.push(0);
+                    }
+                }
+                Inc => tape[head] = tape[head].wrapping_add(1),
+                Dec => tape[head] = tape[head].wrapping_sub(1),
+                Input => {
+                    let mut buf = [0];
+                    std::io::stdin().read_exact(&mut buf).expect("No input");
+                    tape[head] = buf[0] as Word;
+                }
+                Output => print!("{}", tape[head] as u8 as char),
+                LBrac if tape[head] == 0 => {
+                    // Skip to the matching bracket
+                    let mut depth = 0;
+                    loop {
+                        match code.get(ip).ok_or_else(trap)? {
+                            LBrac => depth += 1,
+                            RBrac if depth == 1 => break,
+                            RBrac => depth -= 1,
+                            _ => (),
+                        }
+                        ip += 1;
+                    }
+                }
+                LBrac => bracs.push(ip),
+                RBrac if tape[head] != 0 => ip = *bracs.last().ok_or_else(trap)?,
+                RBrac => {
+                    bracs.pop();
+                }
+                Dbg(_) | Profile(_) => (),
+            }
+            ip += 1;
+        }
+
+        if head != height {
+            return Err(trap());
+        }
+
+        tape.truncate(height + 1);
+        self.stack = tape.split_off(1);
+        Ok(())
     }
 }
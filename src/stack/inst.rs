@@ -1,12 +1,35 @@
 use super::*;
 
-#[derive(Default, Copy, Clone, PartialEq, Hash, Eq, PartialOrd, Ord)]
+// Exit status of `abort()`, & once a `Trap` has stopped the program, the way
+// a shell reports SIGABRT
+pub const ABORT_STATUS: Word = 134;
+
+// Codes of the different `Trap`s
+pub const TRAP_ASSERT: Word = 1;
+pub const TRAP_STACK_OVERFLOW: Word = 2;
+pub const TRAP_INLINE_BF: Word = 3;
+
+// Describes a `Trap`, as it is reported when hit
+pub fn trap_message(code: Word) -> String {
+    let reason = match code {
+        TRAP_ASSERT => "assertion failed",
+        TRAP_STACK_OVERFLOW => "stack overflow",
+        TRAP_INLINE_BF => "inline BF broke its stack effect",
+        _ => "unknown trap",
+    };
+    format!("trap {} ({})", code, reason)
+}
+
+// Label of a `Debug` which pauses execution until enter is pressed
+pub const BREAKPOINT: &str = "breakpoint";
+
+#[derive(Default, Clone, PartialEq, Hash, Eq, PartialOrd, Ord)]
 pub enum StackInst {
     // Misc. + Debug
     #[default]
     Nop,
-    Comment(&'static str),
-    Debug(&'static str),
+    Comment(String),
+    Debug(String),
 
     // Stack Manipulation
     Push(Word),
@@ -58,9 +81,14 @@ pub enum StackInst {
     Branch(Word, Word), // (True label, False label)
     Goto,
     Exit,
+    Trap(Word), // Stops the program with an error
 
     // IO
     PutChar,
+
+    // Inline BF, with its declared (inputs, outputs). Expects the head on the
+    // top of the stack, & must leave it on the new top.
+    Bf(String, usize, usize),
 }
 
 impl StackInst {
@@ -84,28 +112,83 @@ impl StackInst {
                     continue;
                 }
             };
-            for &inst in expansion.iter().rev() {
-                stream.push(inst);
+            for inst in expansion.iter().rev() {
+                stream.push(inst.clone());
             }
         }
 
         *stream = out;
     }
 
+    // Reads instructions written the way they are shown, e.g. `Push(3) Add`
+    pub fn parse_stream(text: &str) -> Result<Vec<Self>, String> {
+        parse_stack_ir(text).map_err(|e| e.message)
+    }
+
+    // The mnemonic an instruction is printed & parsed with
+    pub fn name(&self) -> &'static str {
+        use StackInst::*;
+        match self {
+            Nop => "Nop",
+            Comment(_) => "Comment",
+            Debug(_) => "Debug",
+            Push(_) => "Push",
+            Move(_) => "Move",
+            Swap => "Swap",
+            Copy => "Copy",
+            Add => "Add",
+            Sub => "Sub",
+            Mul => "Mul",
+            Div => "Div",
+            Mod => "Mod",
+            Negate => "Negate",
+            LShift => "LShift",
+            RShift => "RShift",
+            And => "And",
+            Or => "Or",
+            Xor => "Xor",
+            Not => "Not",
+            Eq => "Eq",
+            Neq => "Neq",
+            Lt => "Lt",
+            LtEq => "LtEq",
+            Gr => "Gr",
+            GrEq => "GrEq",
+            LNot => "LNot",
+            LAnd => "LAnd",
+            LOr => "LOr",
+            Alloc(_) => "Alloc",
+            Dealloc(_) => "Dealloc",
+            GblStr => "GblStr",
+            GblRead => "GblRead",
+            LclStr(_) => "LclStr",
+            LclRead(_) => "LclRead",
+            StkRead => "StkRead",
+            StkStr => "StkStr",
+            Label(_) => "Label",
+            Branch(_, _) => "Branch",
+            Goto => "Goto",
+            Exit => "Exit",
+            Trap(_) => "Trap",
+            PutChar => "PutChar",
+            Bf(_, _, _) => "Bf",
+        }
+    }
+
     // # of words of input + # of words of output (if constant)
-    pub fn signature(self) -> (usize, Option<usize>) {
+    pub fn signature(&self) -> (usize, Option<usize>) {
         use StackInst::*;
         match self {
             Comment(_) | Debug(_) | Nop => (0, Some(0)),
             Push(_) => (0, Some(1)),
-            Move(_) => (1, Some(0)),
+            Move(_) => (1, Some(1)),
             Copy => (1, Some(2)),
             Swap => (2, Some(2)),
             LNot | Not => (1, Some(1)),
             Add | Sub | Mul | Div | Eq | Neq | Lt | LtEq | Gr | GrEq | LAnd | LOr | LShift
             | RShift | And | Or | Xor | Mod => (2, Some(1)),
-            Alloc(n) => (0, Some(n)),
-            Dealloc(n) => (n, Some(0)),
+            Alloc(n) => (0, Some(*n)),
+            Dealloc(n) => (*n, Some(0)),
             GblStr | StkStr => (2, Some(0)),
             GblRead | StkRead => (1, Some(1)),
             Negate => (1, Some(1)),
@@ -114,8 +197,9 @@ impl StackInst {
             Label(_) => (0, None),
             Branch(_, _) => (1, Some(0)),
             Goto => (1, Some(0)),
-            Exit => (0, None),
+            Exit | Trap(_) => (0, None),
             PutChar => (1, Some(0)),
+            Bf(_, i, o) => (*i, Some(*o)),
         }
     }
 }
@@ -123,48 +207,17 @@ impl StackInst {
 impl std::fmt::Debug for StackInst {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use StackInst::*;
+        let name = self.name();
         match self {
-            Nop => write!(f, "Nop"),
-            Debug(l) => write!(f, "Debug({})", l),
             Comment(c) => write!(f, "/* {} */", c),
-            Push(c) => write!(f, "Push({})", c),
-            Move(d) => write!(f, "Move({})", d),
-            Swap => write!(f, "Swap"),
-            Copy => write!(f, "Copy"),
-            Add => write!(f, "Add"),
-            Sub => write!(f, "Sub"),
-            Mul => write!(f, "Mul"),
-            Div => write!(f, "Div"),
-            Mod => write!(f, "Mod"),
-            Negate => write!(f, "Negate"),
-            LShift => write!(f, "LShift"),
-            RShift => write!(f, "RShift"),
-            Alloc(n) => write!(f, "Alloc({})", n),
-            Dealloc(n) => write!(f, "Dealloc({})", n),
-            GblStr => write!(f, "GblStr"),
-            GblRead => write!(f, "GblRead"),
-            StkStr => write!(f, "StkStr"),
-            StkRead => write!(f, "StkRead"),
-            LclStr(d) => write!(f, "LclStr({})", d),
-            LclRead(d) => write!(f, "LclRead({})", d),
-            Label(l) => write!(f, "Label({})", l),
-            Branch(t, e) => write!(f, "Branch({}, {})", t, e),
-            Goto => write!(f, "Goto"),
-            Exit => write!(f, "Exit"),
-            PutChar => write!(f, "PrintChar"),
-            Eq => write!(f, "Eq"),
-            Neq => write!(f, "Neq"),
-            Lt => write!(f, "Lt"),
-            LtEq => write!(f, "LtEq"),
-            Gr => write!(f, "Gr"),
-            GrEq => write!(f, "GrEq"),
-            LNot => write!(f, "LNot"),
-            LAnd => write!(f, "LAnd"),
-            LOr => write!(f, "LOr"),
-            And => write!(f, "And"),
-            Or => write!(f, "Or"),
-            Xor => write!(f, "Xor"),
-            Not => write!(f, "Not"),
+            Debug(l) => write!(f, "{}({:?})", name, l),
+            Push(w) | Label(w) | Trap(w) => write!(f, "{}({})", name, w),
+            Move(n) | Alloc(n) | Dealloc(n) | LclStr(n) | LclRead(n) => {
+                write!(f, "{}({})", name, n)
+            }
+            Branch(t, e) => write!(f, "{}({}, {})", name, t, e),
+            Bf(c, i, o) => write!(f, "{}({:?}, {}, {})", name, c, i, o),
+            _ => write!(f, "{}", name),
         }
     }
 }
@@ -1,10 +1,16 @@
 pub mod ctxt;
 pub mod exec;
 pub mod inst;
+pub mod opt;
+pub mod text;
+pub mod verify;
 
 pub use ctxt::*;
 pub use exec::*;
 pub use inst::*;
+pub use opt::*;
+pub use text::*;
+pub use verify::*;
 
 pub use super::*;
 
@@ -0,0 +1,286 @@
+use super::*;
+
+use std::collections::*;
+
+// Rewrites a stream into a cheaper one, which runs the same way. `-O1` folds
+// constants, simplifies short runs of instructions, drops code which can't run
+// & merges blocks which are only ever entered from the one before them, so
+// there are fewer labels to check in BF's main loop. `-O2` also follows
+// constant branches, & threads jumps through blocks that only jump on.
+pub fn optimize_stack_ir(stream: &mut Vec<StackInst>, level: u8) {
+    if level == 0 {
+        return;
+    }
+
+    loop {
+        let old = stream.clone();
+        peephole(stream, level);
+        if level >= 2 {
+            thread_jumps(stream);
+        }
+        remove_dead_code(stream);
+        merge_blocks(stream);
+        if *stream == old {
+            break;
+        }
+    }
+}
+
+// Rewrites the end of the stream as it is built up, so that each rewrite can
+// enable the next
+fn peephole(stream: &mut Vec<StackInst>, level: u8) {
+    let mut out = vec![];
+    for inst in stream.drain(..) {
+        out.push(inst);
+        while let Some((n, new)) = rewrite(&out, level) {
+            out.truncate(out.len() - n);
+            out.extend(new);
+        }
+    }
+    *stream = out;
+}
+
+// The # of instructions at the end of `out` to replace, & what with
+fn rewrite(out: &[StackInst], level: u8) -> Option<(usize, Vec<StackInst>)> {
+    use StackInst::*;
+    let r = match out {
+        [.., Nop | Alloc(0) | Dealloc(0)] => (1, vec![]),
+
+        // Constant folding
+        [.., Push(a), Push(b), op] if fold(op, *a, *b).is_some() => {
+            (3, vec![Push(fold(op, *a, *b).unwrap())])
+        }
+        [.., Push(a), Negate] => (2, vec![Push(a.wrapping_neg())]),
+        [.., Push(a), Not] => (2, vec![Push(!a)]),
+        [.., Push(a), LNot] => (2, vec![Push((*a == 0) as Word)]),
+        [.., Push(c), Branch(t, f)] if level >= 2 => {
+            (2, vec![Push(if *c != 0 { *t } else { *f }), Goto])
+        }
+
+        // Algebra. Subtraction becomes addition, so offsets can be combined.
+        [.., Push(a), Sub] => (2, vec![Push(a.wrapping_neg()), Add]),
+        [.., Push(a), Add, Push(b), Add] => (4, vec![Push(a.wrapping_add(*b)), Add]),
+        [.., Push(0), Add | Or | Xor | LShift | RShift] => (2, vec![]),
+        [.., Push(1), Mul | Div] => (2, vec![]),
+        [.., Swap, Swap] | [.., Negate, Negate] | [.., Not, Not] => (2, vec![]),
+        [.., LNot, LNot, LNot] => (3, vec![LNot]),
+        [
+            ..,
+            Swap,
+            op @ (Add | Mul | And | Or | Xor | Eq | Neq | LAnd | LOr),
+        ] => (2, vec![op.clone()]),
+        [.., Swap, Lt] => (2, vec![Gr]),
+        [.., Swap, Gr] => (2, vec![Lt]),
+        [.., Swap, LtEq] => (2, vec![GrEq]),
+        [.., Swap, GrEq] => (2, vec![LtEq]),
+        [.., Eq, LNot] => (2, vec![Neq]),
+        [.., Neq, LNot] => (2, vec![Eq]),
+        [.., Lt, LNot] => (2, vec![GrEq]),
+        [.., GrEq, LNot] => (2, vec![Lt]),
+        [.., Gr, LNot] => (2, vec![LtEq]),
+        [.., LtEq, LNot] => (2, vec![Gr]),
+        [.., LNot, Branch(t, f)] => (2, vec![Branch(*f, *t)]),
+
+        // Stack shuffling
+        [.., Push(a), Push(b), Swap] => (3, vec![Push(*b), Push(*a)]),
+        [.., Push(_) | Copy | LclRead(_), Dealloc(n)] => (2, vec![Dealloc(n - 1)]),
+        [.., Dealloc(a), Dealloc(b)] => (2, vec![Dealloc(a + b)]),
+        [.., Alloc(a), Alloc(b)] => (2, vec![Alloc(a + b)]),
+        [.., Alloc(a), Dealloc(b)] if a >= b => (2, vec![Alloc(a - b)]),
+        [.., Alloc(a), Dealloc(b)] => (2, vec![Dealloc(b - a)]),
+        [.., LclRead(a), LclStr(b)] if *b == a + 1 => (2, vec![]),
+        [.., LclStr(a), LclRead(b)] if *a == b + 1 => (2, vec![Move(*a)]),
+        _ => return None,
+    };
+    Some(r)
+}
+
+// Applies an operator to constants, the way `StackMachine` does. Anything it
+// would fail on, or which BF may treat differently, is left alone.
+fn fold(op: &StackInst, a: Word, b: Word) -> Option<Word> {
+    use StackInst::*;
+    let out = match op {
+        Add => a.wrapping_add(b),
+        Sub => a.wrapping_sub(b),
+        Mul => a.wrapping_mul(b),
+        Div | Mod if b == 0 => return None,
+        Div => a / b,
+        Mod => a % b,
+        LShift | RShift if b >= Word::BITS as Word => return None,
+        LShift => a << b,
+        RShift => a >> b,
+        And => a & b,
+        Or => a | b,
+        Xor => a ^ b,
+        Eq => (a == b) as Word,
+        Neq => (a != b) as Word,
+        Lt => (a < b) as Word,
+        LtEq => (a <= b) as Word,
+        Gr => (a > b) as Word,
+        GrEq => (a >= b) as Word,
+        LAnd => (a != 0 && b != 0) as Word,
+        LOr => (a != 0 || b != 0) as Word,
+        _ => return None,
+    };
+    Some(out)
+}
+
+// Jumps to a block which only jumps on go straight to where it ends up. The
+// exit is never a target, since only `Goto` stops when it reaches it.
+fn thread_jumps(stream: &mut [StackInst]) {
+    use StackInst::*;
+    let labels = stream
+        .iter()
+        .enumerate()
+        .filter_map(|(i, inst)| match inst {
+            Label(l) => Some((*l, i)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let target = |mut l: Word| {
+        let mut seen = HashSet::new();
+        while seen.insert(l)
+            && let Some(&i) = labels.get(&l)
+            && let [Push(next), Goto, ..] = &stream[i + 1..]
+            && *next != 0
+        {
+            l = *next;
+        }
+        l
+    };
+    let targets = labels
+        .keys()
+        .map(|&l| (l, target(l)))
+        .collect::<HashMap<_, _>>();
+    let target = |l: &Word| targets.get(l).copied().unwrap_or(*l);
+
+    for i in 0..stream.len() {
+        match (&stream[i], stream.get(i + 1)) {
+            (Push(l), Some(Goto)) => stream[i] = Push(target(l)),
+            (Branch(t, f), _) => stream[i] = Branch(target(t), target(f)),
+            _ => (),
+        }
+    }
+}
+
+// Keeps only the blocks reachable from the start, along with the exit. A label
+// is reachable once code that runs jumps to it, or pushes it for a later jump
+// through the stack: return labels & function entries, which are the labels a
+// plain `Push` may be aiming for. Code between a jump & the next label is
+// dropped too, along with the comments before anything dropped.
+fn remove_dead_code(stream: &mut Vec<StackInst>) {
+    use StackInst::*;
+    let labels = stream
+        .iter()
+        .enumerate()
+        .filter_map(|(i, inst)| match inst {
+            Label(l) => Some((*l, i)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    // Blocks are only ever jumped to by name. Return labels never are, & a call
+    // is a jump to a function's entry right before its return label.
+    let mut direct = HashSet::new();
+    for (i, inst) in stream.iter().enumerate() {
+        match (inst, stream.get(i + 1)) {
+            (Push(l), Some(Goto)) => _ = direct.insert(*l),
+            (Branch(t, f), _) => direct.extend([*t, *f]),
+            _ => (),
+        }
+    }
+    let mut blocks = direct.clone();
+    for (i, inst) in stream.iter().enumerate() {
+        if let (Push(l), Some(Goto), Some(Label(r))) = (inst, stream.get(i + 1), stream.get(i + 2))
+            && !direct.contains(r)
+        {
+            blocks.remove(l);
+        }
+    }
+
+    let mut keep = vec![false; stream.len()];
+    if let Some(&i) = labels.get(&0) {
+        keep[i] = true;
+    }
+
+    let mut live = HashSet::from([0, 1]);
+    let mut queue = vec![1];
+    while let Some(l) = queue.pop() {
+        let Some(&start) = labels.get(&l) else {
+            continue;
+        };
+        keep[start] = true;
+
+        for (i, inst) in stream.iter().enumerate().skip(start + 1) {
+            let targets = match (inst, stream.get(i + 1)) {
+                (Label(l), _) => vec![*l],
+                (Push(l), Some(Goto)) => vec![*l],
+                // Otherwise, it's only a label if no jump names it
+                (Push(l), _) if !blocks.contains(l) => vec![*l],
+                (Branch(t, f), _) => vec![*t, *f],
+                _ => vec![],
+            };
+            for l in targets {
+                if live.insert(l) {
+                    queue.push(l);
+                }
+            }
+
+            if let Label(_) = inst {
+                break;
+            }
+            keep[i] = true;
+            if matches!(inst, Goto | Branch(_, _) | Exit | Trap(_)) {
+                break;
+            }
+        }
+    }
+
+    // Comments go with whatever they're in front of
+    let mut next = true;
+    for (i, inst) in stream.iter().enumerate().rev() {
+        match inst {
+            Comment(_) => keep[i] = next,
+            _ => next = keep[i],
+        }
+    }
+
+    let mut i = 0;
+    stream.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+}
+
+// Drops `Push(l) Goto Label(l)` where nothing else refers to `l`
+fn merge_blocks(stream: &mut Vec<StackInst>) {
+    use StackInst::*;
+    let mut refs = HashMap::new();
+    for inst in stream.iter() {
+        match inst {
+            Push(l) => *refs.entry(*l).or_insert(0) += 1,
+            Branch(t, f) => {
+                *refs.entry(*t).or_insert(0) += 1;
+                *refs.entry(*f).or_insert(0) += 1;
+            }
+            _ => (),
+        }
+    }
+
+    let mut out = vec![];
+    let mut i = 0;
+    while i < stream.len() {
+        if let [Push(l), Goto, Label(m), ..] = &stream[i..]
+            && l == m
+            && *l > 1
+            && refs[l] == 1
+        {
+            i += 3;
+            continue;
+        }
+        out.push(stream[i].clone());
+        i += 1;
+    }
+    *stream = out;
+}
@@ -0,0 +1,365 @@
+use super::*;
+
+use std::collections::*;
+
+// The textual form of stack IR, which `show_stack_ir` prints & `parse_stack_ir`
+// reads back:
+//
+//     /* main */
+//     L1:
+//         Alloc(2)
+//         LclRead(1)
+//         Branch(L2, L3)
+//     L2:
+//         Debug("x is set")
+//         Push(L3)
+//         Goto
+//     L3:
+//
+// There is one instruction per line, though any whitespace separates them.
+// `/* ... */` becomes a `Comment`, while `// ...` is dropped. `name:` defines
+// a label, & `Push`, `Label` & `Branch` may refer to labels by name. `L<n>`
+// is always label n (`L0` is the exit); other names get unused numbers.
+// Strings are quoted & escaped the way Rust does.
+//
+// A whole program starts at `L1`, which must come first, & exits by jumping to
+// `L0`, which comes last.
+
+pub fn show_stack_ir(stream: &[StackInst]) -> String {
+    use StackInst::*;
+    let mut s = String::new();
+
+    for (i, inst) in stream.iter().enumerate() {
+        let line = match inst {
+            Label(l) => format!("L{}:", l),
+            Branch(t, f) => format!("    Branch(L{}, L{})", t, f),
+            // A jump target, rather than a plain number
+            Push(l) if stream.get(i + 1) == Some(&Goto) => format!("    Push(L{})", l),
+            inst => format!("    {:?}", inst),
+        };
+        s += &line;
+        s.push('\n');
+    }
+
+    s
+}
+
+// One of each instruction, to look them up by name
+const INSTRUCTIONS: &[StackInst] = {
+    use StackInst::*;
+    &[
+        Nop,
+        Comment(String::new()),
+        Debug(String::new()),
+        Push(0),
+        Move(0),
+        Swap,
+        Copy,
+        Add,
+        Sub,
+        Mul,
+        Div,
+        Mod,
+        Negate,
+        LShift,
+        RShift,
+        And,
+        Or,
+        Xor,
+        Not,
+        Eq,
+        Neq,
+        Lt,
+        LtEq,
+        Gr,
+        GrEq,
+        LNot,
+        LAnd,
+        LOr,
+        Alloc(0),
+        Dealloc(0),
+        GblStr,
+        GblRead,
+        LclStr(0),
+        LclRead(0),
+        StkRead,
+        StkStr,
+        Label(0),
+        Branch(0, 0),
+        Goto,
+        Exit,
+        Trap(0),
+        PutChar,
+        Bf(String::new(), 0, 0),
+    ]
+};
+
+// An instruction's argument, before labels are given numbers
+enum Arg<'a> {
+    Num(usize),
+    Name(&'a str),
+    Str(String),
+}
+
+enum Item<'a> {
+    Def(&'a str),
+    Inst(&'a str, Vec<Arg<'a>>),
+    Comment(&'a str),
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        let found = self.rest().starts_with(s);
+        if found {
+            self.pos += s.len();
+        }
+        found
+    }
+
+    // Skips whitespace on the same line, or any whitespace at all
+    fn skip(&mut self, newlines: bool) {
+        let rest = self.rest();
+        let trimmed =
+            rest.trim_start_matches(|c: char| c.is_whitespace() && (newlines || c != '\n'));
+        self.pos += rest.len() - trimmed.len();
+    }
+
+    fn word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    // Reads everything up to (& including) `end`
+    fn until(&mut self, end: &str) -> Option<&'a str> {
+        let rest = self.rest();
+        let i = rest.find(end)?;
+        self.pos += i + end.len();
+        Some(&rest[..i])
+    }
+
+    fn error(&self, message: impl Into<String>) -> Diagnostic {
+        let start = self.text[..self.pos].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.text[self.pos..]
+            .find('\n')
+            .map_or(self.text.len(), |i| self.pos + i);
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+
+        Diagnostic {
+            message: message.into(),
+            span: Some(Span {
+                file: current_file(),
+                line,
+                col: self.text[start..self.pos].chars().count() + 1,
+                len: 1,
+                text: self.text[start..end].trim_end_matches('\r').to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let mut s = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let c = match self.peek() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some(c @ ('\\' | '"' | '\'')) => c,
+                        Some('u') if self.rest().starts_with("u{") => {
+                            self.pos += 2;
+                            let c = self
+                                .until("}")
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            s.push(c);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    self.pos += 1;
+                    s.push(c);
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn arg(&mut self) -> Result<Arg<'a>> {
+        match self.peek() {
+            Some('"') => {
+                self.pos += 1;
+                Ok(Arg::Str(self.string()?))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let n = self.word();
+                n.parse()
+                    .map(Arg::Num)
+                    .map_err(|_| self.error(format!("invalid number `{}`", n)))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => Ok(Arg::Name(self.word())),
+            _ => Err(self.error("expected a number, label or string")),
+        }
+    }
+
+    fn item(&mut self) -> Result<Item<'a>> {
+        if self.eat("/*") {
+            let text = self
+                .until("*/")
+                .ok_or_else(|| self.error("unterminated comment"))?;
+            return Ok(Item::Comment(text.trim()));
+        }
+
+        let name = self.word();
+        if name.is_empty() {
+            let c = self.peek().unwrap();
+            return Err(self.error(format!("unexpected `{}`", c)));
+        }
+
+        self.skip(false);
+        if self.eat(":") {
+            return Ok(Item::Def(name));
+        }
+
+        let mut args = vec![];
+        if self.eat("(") {
+            self.skip(true);
+            while !self.eat(")") {
+                if !args.is_empty() && !self.eat(",") {
+                    return Err(self.error("expected `,` or `)`"));
+                }
+                self.skip(true);
+                args.push(self.arg()?);
+                self.skip(true);
+            }
+        }
+
+        Ok(Item::Inst(name, args))
+    }
+}
+
+pub fn parse_stack_ir(text: &str) -> Result<Vec<StackInst>> {
+    let mut reader = Reader { text, pos: 0 };
+    let mut items = vec![];
+    loop {
+        reader.skip(true);
+        if reader.eat("//") {
+            reader.until("\n");
+            continue;
+        }
+        if reader.peek().is_none() {
+            break;
+        }
+        let pos = reader.pos;
+        items.push((pos, reader.item()?));
+    }
+
+    // Numbered labels keep their numbers, & named ones take the next free ones
+    let numbered = |name: &str| name.strip_prefix('L').and_then(|n| n.parse::<Word>().ok());
+    let mut labels = HashMap::new();
+    let mut next = items
+        .iter()
+        .filter_map(|(_, item)| match item {
+            Item::Def(name) => numbered(name),
+            Item::Inst(_, args) => args
+                .iter()
+                .filter_map(|a| match a {
+                    Arg::Name(name) => numbered(name),
+                    _ => None,
+                })
+                .max(),
+            Item::Comment(_) => None,
+        })
+        .max()
+        .unwrap_or(0);
+    for (pos, item) in &items {
+        let Item::Def(name) = item else { continue };
+        let label = numbered(name).unwrap_or_else(|| {
+            next += 1;
+            next
+        });
+        if labels.insert(*name, label).is_some() {
+            reader.pos = *pos;
+            return Err(reader.error(format!("label `{}` is defined twice", name)));
+        }
+    }
+
+    let mut out = vec![];
+    for (pos, item) in items {
+        reader.pos = pos;
+        let (name, args) = match item {
+            Item::Def(name) => {
+                out.push(StackInst::Label(labels[name]));
+                continue;
+            }
+            Item::Comment(text) => {
+                out.push(StackInst::Comment(text.to_string()));
+                continue;
+            }
+            Item::Inst(name, args) => (name, args),
+        };
+
+        let word = |n: usize| {
+            Word::try_from(n).map_err(|_| reader.error(format!("`{}` doesn't fit in a word", n)))
+        };
+        let label = |a: &Arg| match a {
+            Arg::Num(n) => word(*n),
+            Arg::Name(l) => labels
+                .get(l)
+                .copied()
+                .or_else(|| numbered(l))
+                .ok_or_else(|| reader.error(format!("undefined label `{}`", l))),
+            Arg::Str(_) => Err(reader.error(format!("bad arguments to `{}`", name))),
+        };
+
+        use Arg::*;
+        use StackInst::*;
+        let Some(inst) = INSTRUCTIONS.iter().find(|i| i.name() == name) else {
+            return Err(reader.error(format!("invalid instruction `{}`", name)));
+        };
+        let inst = match (inst, &args[..]) {
+            (Push(_), [l]) => Push(label(l)?),
+            (Label(_), [l]) => Label(label(l)?),
+            (Branch(_, _), [t, f]) => Branch(label(t)?, label(f)?),
+            (Trap(_), [Num(n)]) => Trap(word(*n)?),
+            (Debug(_), [Str(l)]) => Debug(l.clone()),
+            (Bf(_, _, _), [Str(code), Num(i), Num(o)]) => Bf(code.clone(), *i, *o),
+            (Move(_), [Num(n)]) => Move(*n),
+            (Alloc(_), [Num(n)]) => Alloc(*n),
+            (Dealloc(_), [Num(n)]) => Dealloc(*n),
+            (LclStr(_), [Num(n)]) => LclStr(*n),
+            (LclRead(_), [Num(n)]) => LclRead(*n),
+            // Those without arguments are shown as just their name
+            (inst, []) if format!("{:?}", inst) == name => inst.clone(),
+            _ => return Err(reader.error(format!("bad arguments to `{}`", name))),
+        };
+        out.push(inst);
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,274 @@
+use super::*;
+
+use std::collections::*;
+
+// Checks a finished stream, by following every path through it with the stack
+// effects from `StackInst::signature`. Heights are counted from the bottom of
+// the current function's frame, which starts with the return label its caller
+// pushed.
+//
+// Return labels are those never jumped to directly. A call is a `Goto` to a
+// function followed by a return label, which is somewhere on the stack, & the
+// callee's frame starts there. Once some `Goto` back out of the callee shows
+// how many words it returns, the caller carries on from that label. The targets
+// of calls through pointers aren't known, so the code after them isn't checked.
+//
+// Only `Goto` & `Exit` may reach the exit; `Branch` would run on past it.
+pub fn verify_stack_ir(stream: &[StackInst]) -> Result<()> {
+    use StackInst::*;
+
+    let mut labels = HashMap::new();
+    for (ip, inst) in stream.iter().enumerate() {
+        if let Label(l) = inst
+            && labels.insert(*l, ip).is_some()
+        {
+            return Err(invalid(stream, ip, format!("`L{}` is defined twice", l)));
+        }
+    }
+
+    let mut direct = HashSet::from([0, 1]);
+    for (ip, inst) in stream.iter().enumerate() {
+        let targets = match (inst, stream.get(ip + 1)) {
+            (Push(l), Some(Goto)) => vec![*l],
+            (Branch(t, f), _) => vec![*t, *f],
+            (Exit, _) => vec![0],
+            _ => vec![],
+        };
+        for l in targets {
+            direct.insert(l);
+            if !labels.contains_key(&l) {
+                let msg = format!("jumps to `L{}`, which isn't defined", l);
+                return Err(invalid(stream, ip, msg));
+            }
+        }
+    }
+
+    // Function entries are labels only ever jumped to right before a return label
+    let rets = labels
+        .keys()
+        .copied()
+        .filter(|l| !direct.contains(l))
+        .collect::<HashSet<_>>();
+    let mut funcs = direct.clone();
+    for (ip, inst) in stream.iter().enumerate() {
+        let call = matches!(stream.get(ip + 2), Some(Label(r)) if rets.contains(r));
+        match (inst, stream.get(ip + 1)) {
+            (Push(l), Some(Goto)) if !call => _ = funcs.remove(l),
+            (Branch(t, f), _) => {
+                funcs.remove(t);
+                funcs.remove(f);
+            }
+            _ => (),
+        }
+    }
+
+    // The BF starts with label 1, rather than at the first instruction
+    match stream.iter().find(|i| !matches!(i, Comment(_) | Nop)) {
+        Some(Label(1)) => (),
+        None => return Ok(()),
+        Some(_) => {
+            return Err(Diagnostic::new(
+                "invalid IR: the program must start at `L1`",
+            ));
+        }
+    }
+
+    let mut verifier = Verifier {
+        stream,
+        rets,
+        funcs,
+        labels,
+        heights: HashMap::new(),
+        returns: HashMap::new(),
+        waiting: HashMap::new(),
+        queue: vec![],
+    };
+    let entry = Frame {
+        stack: vec![],
+        func: None,
+    };
+    verifier.jump(0, 1, entry)?;
+
+    while let Some((ip, frame)) = verifier.queue.pop() {
+        verifier.block(ip, frame)?;
+    }
+
+    Ok(())
+}
+
+fn invalid(stream: &[StackInst], ip: usize, msg: String) -> Diagnostic {
+    Diagnostic::new(format!("invalid IR at #{} `{:?}`: {}", ip, stream[ip], msg))
+}
+
+// What is known of the stack on some path: the return label in each word, if any
+#[derive(Clone)]
+struct Frame {
+    stack: Vec<Option<Label>>,
+    func: Option<Label>, // Entry label of the function, if not at top level
+}
+
+struct Verifier<'a> {
+    stream: &'a [StackInst],
+    labels: HashMap<Label, usize>,
+    rets: HashSet<Label>,
+    funcs: HashSet<Label>,
+    heights: HashMap<Label, usize>,
+    returns: HashMap<Label, usize>, // Words each function returns
+    waiting: HashMap<Label, Vec<(usize, Label, Frame)>>, // Callers, until that's known
+    queue: Vec<(usize, Frame)>,
+}
+
+impl Verifier<'_> {
+    fn jump(&mut self, ip: usize, l: Label, frame: Frame) -> Result<()> {
+        // Heights at the exit don't matter
+        if l == 0 {
+            return Ok(());
+        }
+
+        match self.heights.get(&l) {
+            Some(&h) if h != frame.stack.len() => {
+                let msg = format!(
+                    "leaves {} words at `L{}`, where another path leaves {}",
+                    frame.stack.len(),
+                    l,
+                    h
+                );
+                Err(invalid(self.stream, ip, msg))
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.heights.insert(l, frame.stack.len());
+                self.queue.push((self.labels[&l] + 1, frame));
+                Ok(())
+            }
+        }
+    }
+
+    // Follows the code from `ip` until it jumps away
+    fn block(&mut self, mut ip: usize, mut frame: Frame) -> Result<()> {
+        use StackInst::*;
+        loop {
+            let Some(inst) = self.stream.get(ip) else {
+                let msg = "runs off the end of the program".to_string();
+                return Err(invalid(self.stream, ip - 1, msg));
+            };
+            let stack = &mut frame.stack;
+            let h = stack.len();
+
+            let (i, o) = inst.signature();
+            if i > h {
+                let msg = format!("pops {} words, but the frame only has {}", i, h);
+                return Err(invalid(self.stream, ip, msg));
+            }
+            if let LclRead(d) | LclStr(d) | Move(d) = inst
+                && *d >= h
+            {
+                let msg = format!("reaches {} words down, but the frame only has {}", d, h);
+                return Err(invalid(self.stream, ip, msg));
+            }
+
+            match inst {
+                Label(l) => {
+                    let msg = format!("falls through into `L{}` without a jump", l);
+                    return Err(invalid(self.stream, ip, msg));
+                }
+                Push(l) => stack.push(Some(*l).filter(|l| self.rets.contains(l))),
+                Copy => stack.push(stack[h - 1]),
+                Swap => stack.swap(h - 1, h - 2),
+                LclRead(d) => stack.push(stack[h - 1 - d]),
+                LclStr(d) => stack[h - 1 - d] = stack.pop().unwrap(),
+                Move(d) => stack[h - 1 - d] = stack[h - 1],
+                Branch(t, f) => {
+                    if *t == 0 || *f == 0 {
+                        let msg = "branches to `L0`, which only stops after a `Goto`".to_string();
+                        return Err(invalid(self.stream, ip, msg));
+                    }
+                    stack.pop();
+                    self.jump(ip, *t, frame.clone())?;
+                    return self.jump(ip, *f, frame);
+                }
+                Exit | Trap(_) => return Ok(()),
+                Goto => {
+                    stack.pop();
+                    let callee = match self.stream[ip - 1] {
+                        Push(l) => Some(l),
+                        _ => None,
+                    };
+
+                    if let Some(Label(r)) = self.stream.get(ip + 1)
+                        && callee.is_none_or(|f| self.funcs.contains(&f))
+                        && let Some(base) = stack.iter().position(|l| *l == Some(*r))
+                    {
+                        return self.call(ip, callee, *r, base, frame);
+                    }
+                    return match callee {
+                        Some(l) => self.jump(ip, l, frame),
+                        None => self.ret(ip, frame),
+                    };
+                }
+                _ => {
+                    stack.truncate(h - i);
+                    stack.resize(h - i + o.unwrap_or(0), None);
+                }
+            }
+
+            ip += 1;
+        }
+    }
+
+    fn call(
+        &mut self,
+        ip: usize,
+        callee: Option<Label>,
+        r: Label,
+        base: usize,
+        mut frame: Frame,
+    ) -> Result<()> {
+        let Some(f) = callee else {
+            return Ok(());
+        };
+
+        let entry = Frame {
+            stack: frame.stack.split_off(base),
+            func: Some(f),
+        };
+        self.jump(ip, f, entry)?;
+
+        match self.returns.get(&f) {
+            Some(&words) => {
+                frame.stack.resize(base + words, None);
+                self.jump(ip, r, frame)
+            }
+            None => {
+                self.waiting.entry(f).or_default().push((ip, r, frame));
+                Ok(())
+            }
+        }
+    }
+
+    // Jumps back to the caller, through the label beneath the return value
+    fn ret(&mut self, ip: usize, frame: Frame) -> Result<()> {
+        let Some(f) = frame.func else {
+            let msg = "jumps to a computed label outside of a function".to_string();
+            return Err(invalid(self.stream, ip, msg));
+        };
+
+        let words = frame.stack.len();
+        match self.returns.get(&f) {
+            Some(&w) if w != words => {
+                let msg = format!("returns {} words, where another return leaves {}", words, w);
+                Err(invalid(self.stream, ip, msg))
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.returns.insert(f, words);
+                for (ip, r, mut frame) in self.waiting.remove(&f).unwrap_or_default() {
+                    let base = frame.stack.len();
+                    frame.stack.resize(base + words, None);
+                    self.jump(ip, r, frame)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use c2bf::*;
+use std::path::{Path, PathBuf};
+
+fn files(dir: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+    let mut files = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+// Each file in `c_tests/errors` starts with `// error: <part of the message>`
+#[test]
+fn invalid_programs_are_rejected() {
+    for file in files("c_tests/errors") {
+        let text = std::fs::read_to_string(&file).unwrap();
+        let expected = text.lines().next().unwrap();
+        let expected = expected.strip_prefix("// error: ").unwrap();
+
+        let (stream, diagnostics) = Program::build(&[file.display().to_string()]);
+        assert!(stream.is_none(), "{} was accepted", file.display());
+        assert!(
+            diagnostics
+                .iter()
+                .any(|d| d.level == Level::Error && d.message.contains(expected)),
+            "{}: expected `{}`, got {:?}",
+            file.display(),
+            expected,
+            diagnostics
+        );
+    }
+}
+
+// Each file in `c_tests/warnings` compiles, & starts with either
+// `// warning: <part of the message>` or `// no warnings`
+#[test]
+fn warnings_are_reported() {
+    for file in files("c_tests/warnings") {
+        let text = std::fs::read_to_string(&file).unwrap();
+        let expected = text.lines().next().unwrap();
+
+        let (stream, diagnostics) = Program::build(&[file.display().to_string()]);
+        assert!(stream.is_some(), "{}: {:?}", file.display(), diagnostics);
+        match expected.strip_prefix("// warning: ") {
+            Some(expected) => assert!(
+                diagnostics.iter().any(|d| d.message.contains(expected)),
+                "{}: expected `{}`, got {:?}",
+                file.display(),
+                expected,
+                diagnostics
+            ),
+            None => assert!(
+                diagnostics.is_empty(),
+                "{}: expected no warnings, got {:?}",
+                file.display(),
+                diagnostics
+            ),
+        }
+    }
+}
+
+// Units which declare the same object with different types can't be linked
+#[test]
+fn conflicting_units_are_rejected() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("c_tests/linking");
+    let files = ["long_x.c", "int_x.c"].map(|f| dir.join(f).display().to_string());
+    let (stream, diagnostics) = Program::build(&files);
+    assert!(stream.is_none());
+    assert!(
+        diagnostics
+            .iter()
+            .any(|d| d.message.contains("conflicting types for `x`")),
+        "{:?}",
+        diagnostics
+    );
+}
+
+// dbgdonut.c ends its one frame with a `break` that isn't in any loop. It's
+// run through `run`, as parsing it takes more than a test thread's stack.
+#[test]
+fn stray_break_in_example_is_rejected() {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_run"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("c_tests/dbgdonut.c")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success());
+    assert!(
+        stderr.contains("break statement not within loop or switch"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,69 @@
+use c2bf::*;
+use std::path::{Path, PathBuf};
+
+fn ir_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "ir"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+fn build(file: &Path) -> (Option<Vec<StackInst>>, Vec<Diagnostic>) {
+    Program::build(&[file.display().to_string()])
+}
+
+#[test]
+fn examples_verify() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("ir_tests");
+    for file in ir_files(&dir) {
+        let (stream, diagnostics) = build(&file);
+        assert!(stream.is_some(), "{}: {:?}", file.display(), diagnostics);
+    }
+}
+
+// Each starts with `// error: <part of the message>`
+#[test]
+fn invalid_ir_is_rejected() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("ir_tests/invalid");
+    for file in ir_files(&dir) {
+        let text = std::fs::read_to_string(&file).unwrap();
+        let expected = text.lines().next().unwrap();
+        let expected = expected.strip_prefix("// error: ").unwrap();
+
+        let (stream, diagnostics) = build(&file);
+        assert!(stream.is_none(), "{} was accepted", file.display());
+        let message = &diagnostics[0].message;
+        assert!(
+            message.contains(expected),
+            "{}: expected `{}`, got `{}`",
+            file.display(),
+            expected,
+            message
+        );
+    }
+}
+
+// Each `<name>.ir` becomes `<name>.out` at `-O1`
+#[test]
+fn optimized_ir_matches() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("ir_tests/opt");
+    for file in ir_files(&dir) {
+        let args = ["-O1".to_string(), file.display().to_string()];
+        let (stream, diagnostics) = Program::build(&args);
+        let Some(stream) = stream else {
+            panic!("{}: {:?}", file.display(), diagnostics);
+        };
+
+        let expected = std::fs::read_to_string(file.with_extension("out")).unwrap();
+        let expected = parse_stack_ir(&expected).unwrap();
+        assert_eq!(
+            show_stack_ir(&stream),
+            show_stack_ir(&expected),
+            "{}",
+            file.display()
+        );
+    }
+}
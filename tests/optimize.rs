@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Programs which never finish, or don't print the same thing each run, bare
+// expressions for the parser, & dbgdonut.c, which is rejected
+const SKIP: &[&str] = &[
+    "donut.c",
+    "simple.c",
+    "debug.c",
+    "binops.c",
+    "const.c",
+    "decl.c",
+    "sizeof.c",
+    "dbgdonut.c",
+];
+
+fn run(file: &Path, level: &str) -> (String, Option<i32>) {
+    let out = Command::new(env!("CARGO_BIN_EXE_run"))
+        .arg(level)
+        .arg(file)
+        .output()
+        .unwrap();
+    (
+        String::from_utf8_lossy(&out.stdout).into_owned(),
+        out.status.code(),
+    )
+}
+
+fn examples(dir: &str, ext: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+    let mut files = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == ext))
+        .filter(|p| !SKIP.contains(&p.file_name().unwrap().to_str().unwrap()))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+// Every example runs the same way at each optimization level
+#[test]
+fn examples_match_unoptimized() {
+    let files = examples("c_tests", "c")
+        .into_iter()
+        .chain(examples("ir_tests", "ir"));
+    for file in files {
+        let expected = run(&file, "-O0");
+        assert!(
+            expected.0.contains("Execution:"),
+            "{} doesn't compile",
+            file.display()
+        );
+        for level in ["-O1", "-O2"] {
+            assert_eq!(
+                run(&file, level),
+                expected,
+                "{} at {}",
+                file.display(),
+                level
+            );
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::process::Command;
+
+// Arguments to `run`, then what the program prints & its exit status
+const PROGRAMS: &[(&[&str], &str, i32)] = &[
+    (&["c_tests/tags.c"], "", 19),
+    (&["c_tests/shadowing.c"], "", 61),
+    (&["c_tests/static_shadowing.c"], "", 9),
+    (&["c_tests/enum_scopes.c"], "", 28),
+    (&["c_tests/pointer_steps.c"], "797168\n", 3),
+    (&["c_tests/tentative.c"], "", 7),
+    (&["c_tests/block_storage.c"], "3\n", 30),
+    (&["c_tests/bf_effect.c"], "a", 134),
+    (&["c_tests/escapes.c"], "ABCD\n", 4),
+    (&["c_tests/unprototyped.c"], "", 7),
+    (&["c_tests/long_calls.c"], "111", 10),
+    (&["c_tests/longs.c"], "1111101111111111101111\n", 0),
+    (&["-fstack-limit=160", "c_tests/stack_limit.c"], "", 9),
+    (
+        &["-fstack-limit=140", "c_tests/stack_limit.c"],
+        "stack overflow in `sum`\n",
+        134,
+    ),
+    (&["c_tests/bool.c"], "1011112Y1\n", 0),
+    (&["c_tests/bitfields.c"], "4251-0-12-13517\n", 0),
+    (&["c_tests/conversions.c"], "111010031341111011111\n", 0),
+    (&["c_tests/malloc.c"], "987654321000NNNNN\n", 0),
+    (&["c_tests/compound_literals.c"], "6740967135\n", 0),
+    (&["c_tests/continue.c"], "0246 36 134 236 0.2.!.4.\n", 0),
+    (&["c_tests/const_globals.c"], "843736\n", 0),
+    (
+        &["c_tests/linking/main.c", "c_tests/linking/util.c"],
+        "4178",
+        0,
+    ),
+    (
+        &["c_tests/linking/enum_n.c", "c_tests/linking/var_n.c"],
+        "",
+        7,
+    ),
+];
+
+#[test]
+fn programs_run() {
+    for (args, output, status) in PROGRAMS {
+        let out = Command::new(env!("CARGO_BIN_EXE_run"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(*args)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let printed = stdout.split_once("Execution:\n\n").map(|(_, p)| p);
+        assert_eq!(printed, Some(*output), "{:?}: {}", args, stdout);
+        assert_eq!(out.status.code(), Some(*status), "{:?}", args);
+    }
+}